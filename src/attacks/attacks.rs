@@ -33,7 +33,7 @@ pub fn pawn_east_att(square: u8, color: Color) -> Bitboard {
     let occ = 1u64 << square;
     match color {
         Color::White => (occ << 9) & !mask_file(0),
-        Color::Black => (occ >> 7) & !mask_file(63),
+        Color::Black => (occ >> 7) & !mask_file(0),
     }
 }
 
@@ -42,7 +42,7 @@ pub fn pawn_west_att(square: u8, color: Color) -> Bitboard {
     let occ = 1u64 << square;
     match color {
         Color::White => (occ << 7) & !mask_file(63),
-        Color::Black => (occ >> 9) & !mask_file(0),
+        Color::Black => (occ >> 9) & !mask_file(63),
     }
 }
 
@@ -154,4 +154,13 @@ mod tests {
         let bb = hyperbola_quintessence(1 << 18, 1 << 9 | 1 << 45, mask_diag(18));
         print_bitboard(bb);
     }
+
+    #[test]
+    pub fn calculate_pawn_attacks_without_wrapping() {
+        assert!(pawn_att(40, Color::Black) == 1 << 33);
+        assert!(pawn_att(47, Color::Black) == 1 << 38);
+        assert!(pawn_att(24, Color::White) == 1 << 33);
+        assert!(pawn_att(31, Color::White) == 1 << 38);
+        assert!(pawn_att(20, Color::Black) == (1 << 11 | 1 << 13));
+    }
 }
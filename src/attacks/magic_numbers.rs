@@ -70,7 +70,7 @@ pub fn init_magic(
                 magic,
                 mask: attack_mask,
                 shift,
-                offset: offset.clone(),
+                offset: *offset,
             };
 
             for idx in 0..permutations_count {
@@ -87,7 +87,7 @@ pub fn init_magic(
 
 /// Uses a magic number to calculate the index for the given occupancy in the precomputed table
 pub fn calculate_hash_index(magic: u64, occupancy: Bitboard, shift: u8) -> usize {
-    (((occupancy.wrapping_mul(magic)) >> shift) as u64) as usize
+    (occupancy.wrapping_mul(magic) >> shift) as usize
 }
 
 /// Verifies if a magic number candidate is suitable by checking if the multiplication
@@ -1,13 +1,21 @@
-use crate::util::{Bitboard, Color};
+use std::{ptr::addr_of_mut, sync::Once};
+
+use crate::{
+    piece::PieceTypes,
+    util::{bb_from_square, Bitboard, Color},
+};
 
 use self::{
-    attacks::{king_att, knight_att, pawn_att},
+    attacks::{bishop_att, king_att, knight_att, pawn_att, rook_att},
     magic_numbers::{calculate_hash_index, init_magic, Magic, SLIDER_TABLE_SIZE},
 };
 
+#[allow(clippy::module_inception)]
 pub mod attacks;
 pub mod magic_numbers;
 
+static INIT: Once = Once::new();
+
 pub static mut SLIDER_ATTACKS: [Bitboard; SLIDER_TABLE_SIZE] = [0; SLIDER_TABLE_SIZE];
 pub static mut BISHOP_TABLE: [Magic; 64] = [Magic {
     mask: 0,
@@ -25,23 +33,55 @@ pub static mut KNIGHT_TABLE: [Bitboard; 64] = [0; 64];
 pub static mut KING_TABLE: [Bitboard; 64] = [0; 64];
 pub static mut PAWN_WHITE_TABLE: [Bitboard; 64] = [0; 64];
 pub static mut PAWN_BLACK_TABLE: [Bitboard; 64] = [0; 64];
+pub static mut BETWEEN_TABLE: [[Bitboard; 64]; 64] = [[0; 64]; 64];
+pub static mut LINE_TABLE: [[Bitboard; 64]; 64] = [[0; 64]; 64];
 
-/// Initialize all attack tables for quick lookups
+/// Initialize all attack tables for quick lookups. Only the first call does any work.
 pub fn init_tables() {
-    let mut offset = 0;
+    INIT.call_once(|| {
+        let mut offset = 0;
+        let slider_attacks = unsafe { &mut *addr_of_mut!(SLIDER_ATTACKS) };
+
+        for idx in 0..64 {
+            let magic = init_magic(idx, true, &mut offset, slider_attacks);
+            unsafe { BISHOP_TABLE[idx as usize] = magic };
 
-    for idx in 0..64 {
-        let magic = unsafe { init_magic(idx, true, &mut offset, &mut SLIDER_ATTACKS) };
-        unsafe { BISHOP_TABLE[idx as usize] = magic };
+            let magic = init_magic(idx, false, &mut offset, slider_attacks);
+            unsafe { ROOK_TABLE[idx as usize] = magic };
 
-        let magic = unsafe { init_magic(idx, false, &mut offset, &mut SLIDER_ATTACKS) };
-        unsafe { ROOK_TABLE[idx as usize] = magic };
+            unsafe { KNIGHT_TABLE[idx as usize] = knight_att(idx) }
+            unsafe { KING_TABLE[idx as usize] = king_att(idx) }
+            unsafe { PAWN_WHITE_TABLE[idx as usize] = pawn_att(idx, Color::White) }
+            unsafe { PAWN_BLACK_TABLE[idx as usize] = pawn_att(idx, Color::Black) }
+        }
+
+        for from in 0..64u8 {
+            for to in 0..64u8 {
+                let (between, line) = between_and_line(from, to);
+                unsafe { BETWEEN_TABLE[from as usize][to as usize] = between }
+                unsafe { LINE_TABLE[from as usize][to as usize] = line }
+            }
+        }
+    });
+}
 
-        unsafe { KNIGHT_TABLE[idx as usize] = knight_att(idx) }
-        unsafe { KING_TABLE[idx as usize] = king_att(idx) }
-        unsafe { PAWN_WHITE_TABLE[idx as usize] = pawn_att(idx, Color::White) }
-        unsafe { PAWN_BLACK_TABLE[idx as usize] = pawn_att(idx, Color::Black) }
+/// Calculates the squares strictly between two aligned squares and the full line through them.
+/// Both are empty if the squares do not share a rank, file or diagonal.
+fn between_and_line(from: u8, to: u8) -> (Bitboard, Bitboard) {
+    if from == to {
+        return (0, 0);
+    }
+    let from_bb = bb_from_square(from);
+    let to_bb = bb_from_square(to);
+
+    for slider_att in [rook_att, bishop_att] {
+        if slider_att(from, 0) & to_bb != 0 {
+            let between = slider_att(from, to_bb) & slider_att(to, from_bb);
+            let line = (slider_att(from, 0) & slider_att(to, 0)) | from_bb | to_bb;
+            return (between, line);
+        }
     }
+    (0, 0)
 }
 
 /// Looks up slider attacks in the precomputed table
@@ -49,20 +89,18 @@ pub fn lookup_slider_att(square: u8, occupancy: Bitboard, bishop: bool) -> Bitbo
     if square > 63 {
         return 0;
     }
-    let table = unsafe {
-        if bishop {
-            BISHOP_TABLE
-        } else {
-            ROOK_TABLE
-        }
-    };
-
     let Magic {
         magic,
         mask,
         shift,
         offset,
-    } = table[square as usize];
+    } = unsafe {
+        if bishop {
+            BISHOP_TABLE[square as usize]
+        } else {
+            ROOK_TABLE[square as usize]
+        }
+    };
 
     let hash_index = calculate_hash_index(magic, occupancy & mask, shift);
     unsafe { SLIDER_ATTACKS[offset + hash_index] }
@@ -95,11 +133,37 @@ pub fn lookup_knight_att(square: u8) -> Bitboard {
 
 /// Looks up pawn attacks in the precomputed attack table
 pub fn lookup_pawn_att(square: u8, color: Color) -> Bitboard {
-    let table = unsafe {
+    unsafe {
         match color {
-            Color::Black => PAWN_BLACK_TABLE,
-            Color::White => PAWN_WHITE_TABLE,
+            Color::Black => PAWN_BLACK_TABLE[square as usize],
+            Color::White => PAWN_WHITE_TABLE[square as usize],
         }
-    };
-    table[square as usize]
+    }
+}
+
+/// Looks up the squares strictly between two squares on a common rank, file or diagonal
+pub fn lookup_between(from: u8, to: u8) -> Bitboard {
+    unsafe { BETWEEN_TABLE[from as usize][to as usize] }
+}
+
+/// Looks up the full line through two squares on a common rank, file or diagonal
+pub fn lookup_line(from: u8, to: u8) -> Bitboard {
+    unsafe { LINE_TABLE[from as usize][to as usize] }
+}
+
+/// Looks up the attacks of any piece type in the precomputed attack tables
+pub fn lookup_piece_att(
+    piece_type: PieceTypes,
+    square: u8,
+    color: Color,
+    occupancy: Bitboard,
+) -> Bitboard {
+    match piece_type {
+        PieceTypes::PAWN => lookup_pawn_att(square, color),
+        PieceTypes::KNIGHT => lookup_knight_att(square),
+        PieceTypes::BISHOP => lookup_bishop_att(square, occupancy),
+        PieceTypes::ROOK => lookup_rook_att(square, occupancy),
+        PieceTypes::QUEEN => lookup_queen_att(square, occupancy),
+        _ => lookup_king_att(square),
+    }
 }
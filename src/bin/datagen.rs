@@ -0,0 +1,200 @@
+//! Generates training data by playing self-play games.
+//!
+//! Usage: `datagen [--games <n>] [--threads <n>] [--nodes <n> | --depth <n>] [--random-plies <n>]
+//! [--hash <mb>] [--output <name>]`
+//!
+//! The games start from random openings and every move is searched with the given node or depth
+//! limit. Quiet positions are written with their search score and the game result to
+//! `<name>.txt` and in the packed format to `<name>.bin`, see `rust_chess_engine::datagen`.
+//!
+//! `datagen --convert <file.bin> <file.txt>` converts packed data back to text.
+
+use std::{
+    env,
+    fs::File,
+    io::{self, BufReader, BufWriter, Write},
+    process,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc,
+    },
+    thread,
+    time::Instant,
+};
+
+use rust_chess_engine::{
+    attacks::init_tables,
+    datagen::{play_game, read_packed, write_packed, write_text, DataEntry, DatagenConfig},
+    limits::SearchLimits,
+    transposition_table::TranspositionTable,
+};
+
+const USAGE: &str = "usage: datagen [--games <n>] [--threads <n>] [--nodes <n> | --depth <n>] \
+                     [--random-plies <n>] [--hash <mb>] [--output <name>]\n       \
+                     datagen --convert <file.bin> <file.txt>";
+
+struct Options {
+    games: usize,
+    threads: usize,
+    config: DatagenConfig,
+    hash: usize,
+    output: String,
+    convert: Option<(String, String)>,
+}
+
+fn parse_args() -> Result<Options, String> {
+    let mut args = env::args().skip(1);
+    let mut options = Options {
+        games: 100,
+        threads: thread::available_parallelism().map_or(1, |n| n.get()),
+        config: DatagenConfig {
+            limits: SearchLimits {
+                nodes: Some(5000),
+                ..SearchLimits::default()
+            },
+            random_plies: 8,
+        },
+        hash: 16,
+        output: String::from("data"),
+        convert: None,
+    };
+
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| {
+            args.next()
+                .ok_or_else(|| format!("missing value for {}", name))
+        };
+        match arg.as_str() {
+            "--games" => {
+                options.games = value("--games")?
+                    .parse()
+                    .map_err(|_| "invalid number of games")?
+            }
+            "--threads" => {
+                options.threads = value("--threads")?
+                    .parse()
+                    .map_err(|_| "invalid number of threads")?
+            }
+            "--nodes" => {
+                options.config.limits.nodes = Some(
+                    value("--nodes")?
+                        .parse()
+                        .map_err(|_| "invalid node limit")?,
+                );
+                options.config.limits.depth = None;
+            }
+            "--depth" => {
+                options.config.limits.depth =
+                    Some(value("--depth")?.parse().map_err(|_| "invalid depth")?);
+                options.config.limits.nodes = None;
+            }
+            "--random-plies" => {
+                options.config.random_plies = value("--random-plies")?
+                    .parse()
+                    .map_err(|_| "invalid number of random plies")?
+            }
+            "--hash" => options.hash = value("--hash")?.parse().map_err(|_| "invalid hash size")?,
+            "--output" => options.output = value("--output")?,
+            "--convert" => options.convert = Some((value("--convert")?, value("--convert")?)),
+            _ => return Err(format!("unexpected argument: {}", arg)),
+        }
+    }
+    Ok(options)
+}
+
+fn convert(input: &str, output: &str) -> io::Result<usize> {
+    let entries = read_packed(BufReader::new(File::open(input)?))?;
+    let mut writer = BufWriter::new(File::create(output)?);
+    write_text(&mut writer, &entries)?;
+    writer.flush()?;
+    Ok(entries.len())
+}
+
+fn generate(options: &Options) -> io::Result<usize> {
+    let text = BufWriter::new(File::create(format!("{}.txt", options.output))?);
+    let packed = BufWriter::new(File::create(format!("{}.bin", options.output))?);
+    let next_game = AtomicUsize::new(0);
+    let (sender, receiver) = mpsc::channel();
+    let start = Instant::now();
+
+    thread::scope(|scope| {
+        for _ in 0..options.threads.max(1) {
+            let sender = sender.clone();
+            let next_game = &next_game;
+            scope.spawn(move || {
+                let tt = TranspositionTable::new(options.hash);
+                let mut rng = rand::thread_rng();
+                while next_game.fetch_add(1, Ordering::Relaxed) < options.games {
+                    if sender
+                        .send(play_game(&options.config, &tt, &mut rng))
+                        .is_err()
+                    {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(sender);
+
+        write_games(receiver, text, packed, options.games, start)
+    })
+}
+
+/// Writes the games sent by the workers until all of them are finished. The receiver is dropped
+/// when writing fails, so the workers stop after their current game.
+fn write_games(
+    receiver: mpsc::Receiver<Vec<DataEntry>>,
+    mut text: impl Write,
+    mut packed: impl Write,
+    total_games: usize,
+    start: Instant,
+) -> io::Result<usize> {
+    let mut positions = 0;
+    for (games, entries) in receiver.iter().enumerate() {
+        write_text(&mut text, &entries)?;
+        write_packed(&mut packed, &entries)?;
+        positions += entries.len();
+        if (games + 1) % 10 == 0 || games + 1 == total_games {
+            println!(
+                "{} games, {} positions, {:.1} positions/s",
+                games + 1,
+                positions,
+                positions as f64 / start.elapsed().as_secs_f64()
+            );
+        }
+    }
+    text.flush()?;
+    packed.flush()?;
+    Ok(positions)
+}
+
+fn main() {
+    let options = parse_args().unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        eprintln!("{}", USAGE);
+        process::exit(1);
+    });
+    init_tables();
+
+    if let Some((input, output)) = &options.convert {
+        match convert(input, output) {
+            Ok(count) => println!("Converted {} positions to {}", count, output),
+            Err(err) => {
+                eprintln!("cannot convert {}: {}", input, err);
+                process::exit(1);
+            }
+        }
+        return;
+    }
+
+    match generate(&options) {
+        Ok(positions) => println!(
+            "Wrote {} positions to {}.txt and {}.bin",
+            positions, options.output, options.output
+        ),
+        Err(err) => {
+            eprintln!("cannot write training data: {}", err);
+            process::exit(1);
+        }
+    }
+}
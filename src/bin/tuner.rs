@@ -0,0 +1,112 @@
+//! Tunes the evaluation weights on positions labelled with game results.
+//!
+//! Usage: `tuner <data file> [--output <file>] [--passes <n>] [--step <n>] [--threads <n>]`
+//!
+//! Each line of the data file holds a FEN and the game result, e.g.
+//! `rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 [1/2-1/2]`. Quiet positions give the
+//! best results since the evaluation is used without a search. The tuned weights are written as
+//! Rust constants after every pass.
+
+use std::{env, fs, process, thread, time::Instant};
+
+use rust_chess_engine::{
+    attacks::init_tables,
+    evaluation::params::DEFAULT_PARAMS,
+    tuner::{load_entries, Tuner},
+};
+
+struct Options {
+    data_file: String,
+    output: String,
+    passes: usize,
+    step: i32,
+    threads: usize,
+}
+
+fn parse_args() -> Result<Options, String> {
+    let mut args = env::args().skip(1);
+    let mut options = Options {
+        data_file: String::new(),
+        output: String::from("tuned_params.rs"),
+        passes: 100,
+        step: 1,
+        threads: thread::available_parallelism().map_or(1, |n| n.get()),
+    };
+
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| {
+            args.next()
+                .ok_or_else(|| format!("missing value for {}", name))
+        };
+        match arg.as_str() {
+            "--output" => options.output = value("--output")?,
+            "--passes" => {
+                options.passes = value("--passes")?
+                    .parse()
+                    .map_err(|_| "invalid number of passes")?
+            }
+            "--step" => options.step = value("--step")?.parse().map_err(|_| "invalid step size")?,
+            "--threads" => {
+                options.threads = value("--threads")?
+                    .parse()
+                    .map_err(|_| "invalid number of threads")?
+            }
+            _ if options.data_file.is_empty() => options.data_file = arg,
+            _ => return Err(format!("unexpected argument: {}", arg)),
+        }
+    }
+
+    if options.data_file.is_empty() {
+        return Err(String::from("missing data file"));
+    }
+    Ok(options)
+}
+
+fn main() {
+    let options = parse_args().unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        eprintln!(
+            "usage: tuner <data file> [--output <file>] [--passes <n>] [--step <n>] [--threads <n>]"
+        );
+        process::exit(1);
+    });
+    init_tables();
+
+    let (entries, skipped) = load_entries(&options.data_file).unwrap_or_else(|err| {
+        eprintln!("cannot read {}: {}", options.data_file, err);
+        process::exit(1);
+    });
+    println!(
+        "Loaded {} positions, skipped {} lines",
+        entries.len(),
+        skipped
+    );
+
+    let tuner = Tuner::new(entries, options.threads);
+    let k = tuner.fit_k(&DEFAULT_PARAMS);
+    println!(
+        "K = {:.4}, initial error {:.6}",
+        k,
+        tuner.error(&DEFAULT_PARAMS, k)
+    );
+
+    let start = Instant::now();
+    tuner.tune(
+        &DEFAULT_PARAMS,
+        k,
+        options.step,
+        options.passes,
+        |pass, params, error| {
+            println!(
+                "Pass {}: error {:.6} after {}s",
+                pass,
+                error,
+                start.elapsed().as_secs()
+            );
+            if let Err(err) = fs::write(&options.output, params.to_rust_source()) {
+                eprintln!("cannot write {}: {}", options.output, err);
+            }
+        },
+    );
+    println!("Tuned parameters written to {}", options.output);
+}
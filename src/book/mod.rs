@@ -0,0 +1,418 @@
+use std::{
+    fs::File,
+    io::{self, BufReader, Read, Seek, SeekFrom},
+    path::Path,
+};
+
+use rand::Rng;
+
+use crate::{
+    attacks::lookup_pawn_att,
+    castling_rights::CastlingRights,
+    cmove::{Move, MoveFlags},
+    piece::PieceTypes,
+    position::Position,
+    util::{enumerate_bits, opp, sq_to_file, sq_to_rank, Color},
+};
+
+use self::polyglot_keys::{CASTLING_OFFSET, EN_PASSANT_OFFSET, POLYGLOT_RANDOM, TURN_OFFSET};
+
+pub mod polyglot_keys;
+
+/// Size of a single entry in a Polyglot book file
+const ENTRY_SIZE: u64 = 16;
+
+/// A single entry of a Polyglot book, all fields are stored big-endian in the file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BookEntry {
+    pub key: u64,
+    pub raw_move: u16,
+    pub weight: u16,
+    pub learn: u32,
+}
+
+impl BookEntry {
+    /// Decodes an entry from its 16 byte representation
+    pub fn from_bytes(bytes: &[u8; ENTRY_SIZE as usize]) -> Self {
+        BookEntry {
+            key: u64::from_be_bytes(bytes[0..8].try_into().unwrap()),
+            raw_move: u16::from_be_bytes(bytes[8..10].try_into().unwrap()),
+            weight: u16::from_be_bytes(bytes[10..12].try_into().unwrap()),
+            learn: u32::from_be_bytes(bytes[12..16].try_into().unwrap()),
+        }
+    }
+
+    /// Encodes the entry into its 16 byte representation
+    pub fn to_bytes(&self) -> [u8; ENTRY_SIZE as usize] {
+        let mut bytes = [0u8; ENTRY_SIZE as usize];
+        bytes[0..8].copy_from_slice(&self.key.to_be_bytes());
+        bytes[8..10].copy_from_slice(&self.raw_move.to_be_bytes());
+        bytes[10..12].copy_from_slice(&self.weight.to_be_bytes());
+        bytes[12..16].copy_from_slice(&self.learn.to_be_bytes());
+        bytes
+    }
+}
+
+/// Opening book in the Polyglot format. Entries are sorted by key, so lookups binary search the
+/// file instead of loading it into memory.
+pub struct Book<R: Read + Seek> {
+    reader: R,
+    len: u64,
+}
+
+impl Book<BufReader<File>> {
+    /// Opens a Polyglot `.bin` book file
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Book::new(BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R: Read + Seek> Book<R> {
+    /// Creates a book from any seekable source of Polyglot entries
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        let len = reader.seek(SeekFrom::End(0))? / ENTRY_SIZE;
+        Ok(Book { reader, len })
+    }
+
+    /// Returns the number of entries in the book
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Checks if the book contains no entries
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn read_entry(&mut self, idx: u64) -> io::Result<BookEntry> {
+        let mut bytes = [0u8; ENTRY_SIZE as usize];
+        self.reader.seek(SeekFrom::Start(idx * ENTRY_SIZE))?;
+        self.reader.read_exact(&mut bytes)?;
+        Ok(BookEntry::from_bytes(&bytes))
+    }
+
+    /// Returns all entries stored for the position
+    pub fn entries(&mut self, position: &Position) -> io::Result<Vec<BookEntry>> {
+        let key = polyglot_hash(position);
+
+        // Lower bound: index of the first entry with a key not less than the searched key
+        let (mut low, mut high) = (0, self.len);
+        while low < high {
+            let mid = low + (high - low) / 2;
+            if self.read_entry(mid)?.key < key {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+
+        let mut entries = Vec::new();
+        for idx in low..self.len {
+            let entry = self.read_entry(idx)?;
+            if entry.key != key {
+                break;
+            }
+            entries.push(entry);
+        }
+        Ok(entries)
+    }
+
+    /// Returns the legal book moves of the position together with their weights
+    pub fn moves(&mut self, position: &Position) -> io::Result<Vec<(Move, u16)>> {
+        Ok(self
+            .entries(position)?
+            .iter()
+            .filter_map(|entry| Some((decode_move(position, entry.raw_move)?, entry.weight)))
+            .collect())
+    }
+
+    /// Returns the book move with the highest weight, preferring the first entry on ties
+    pub fn best_move(&mut self, position: &Position) -> io::Result<Option<Move>> {
+        let moves = self.moves(position)?;
+        Ok(moves
+            .iter()
+            .filter(|(_, weight)| *weight > 0)
+            .fold(
+                None,
+                |best: Option<(Move, u16)>, &(mv, weight)| match best {
+                    Some((_, best_weight)) if best_weight >= weight => best,
+                    _ => Some((mv, weight)),
+                },
+            )
+            .map(|(mv, _)| mv))
+    }
+
+    /// Picks a book move at random with a probability proportional to its weight
+    pub fn weighted_move<G: Rng>(
+        &mut self,
+        position: &Position,
+        rng: &mut G,
+    ) -> io::Result<Option<Move>> {
+        let moves = self.moves(position)?;
+        let total: u32 = moves.iter().map(|(_, weight)| *weight as u32).sum();
+        if total == 0 {
+            return Ok(None);
+        }
+
+        let mut pick = rng.gen_range(0..total);
+        for (mv, weight) in moves {
+            if pick < weight as u32 {
+                return Ok(Some(mv));
+            }
+            pick -= weight as u32;
+        }
+        unreachable!()
+    }
+}
+
+/// Computes the Polyglot hash of a position. It differs from the engine's own Zobrist hash, as it
+/// has to match the keys every Polyglot book was written with.
+pub fn polyglot_hash(position: &Position) -> u64 {
+    let mut hash = 0;
+
+    for color in [Color::White, Color::Black] {
+        for (type_idx, piece_type) in [
+            PieceTypes::PAWN,
+            PieceTypes::KNIGHT,
+            PieceTypes::BISHOP,
+            PieceTypes::ROOK,
+            PieceTypes::QUEEN,
+            PieceTypes::KING,
+        ]
+        .iter()
+        .enumerate()
+        {
+            let kind = 2 * type_idx + if color == Color::White { 1 } else { 0 };
+            enumerate_bits(position.piece_bb(color, *piece_type), |square| {
+                hash ^= POLYGLOT_RANDOM[64 * kind + square as usize];
+            });
+        }
+    }
+
+    for (idx, right) in [
+        CastlingRights::WHITE_KING_SIDE,
+        CastlingRights::WHITE_QUEEN_SIDE,
+        CastlingRights::BLACK_KING_SIDE,
+        CastlingRights::BLACK_QUEEN_SIDE,
+    ]
+    .iter()
+    .enumerate()
+    {
+        if position.castling_rights().contains(*right) {
+            hash ^= POLYGLOT_RANDOM[CASTLING_OFFSET + idx];
+        }
+    }
+
+    // The en passant file only counts if a pawn of the side to move stands next to the pawn
+    let side = position.side_to_move();
+    if let Some(ep_sq) = position.en_passant_square() {
+        if lookup_pawn_att(ep_sq, opp(side)) & position.piece_bb(side, PieceTypes::PAWN) != 0 {
+            hash ^= POLYGLOT_RANDOM[EN_PASSANT_OFFSET + sq_to_file(ep_sq) as usize];
+        }
+    }
+
+    if side == Color::White {
+        hash ^= POLYGLOT_RANDOM[TURN_OFFSET];
+    }
+    hash
+}
+
+/// Translates a Polyglot move to the matching legal move of the position. Polyglot encodes
+/// castling as the king capturing its own rook, e.g. `e1h1` for white castling king side.
+pub fn decode_move(position: &Position, raw_move: u16) -> Option<Move> {
+    let to_file = (raw_move & 7) as u8;
+    let to_rank = ((raw_move >> 3) & 7) as u8;
+    let from_file = ((raw_move >> 6) & 7) as u8;
+    let from_rank = ((raw_move >> 9) & 7) as u8;
+    let promotion = match (raw_move >> 12) & 7 {
+        0 => None,
+        1 => Some(PieceTypes::KNIGHT),
+        2 => Some(PieceTypes::BISHOP),
+        3 => Some(PieceTypes::ROOK),
+        4 => Some(PieceTypes::QUEEN),
+        _ => return None,
+    };
+
+    let from_sq = from_rank * 8 + from_file;
+    let mut to_sq = to_rank * 8 + to_file;
+
+    let is_king = position
+        .piece_at(from_sq)
+        .is_some_and(|piece| piece.get_piece_type() == PieceTypes::KING);
+    if is_king && sq_to_rank(from_sq) == sq_to_rank(to_sq) && from_file == 4 {
+        match to_file {
+            7 => to_sq = from_sq + 2,
+            0 => to_sq = from_sq - 2,
+            _ => (),
+        }
+    }
+
+    position.generate_moves().iter().copied().find(|mv| {
+        mv.get_from() == from_sq
+            && mv.get_to() == to_sq
+            && mv.get_promotion_piece_type() == promotion
+    })
+}
+
+/// Translates a legal move to its Polyglot encoding
+pub fn encode_move(mv: Move) -> u16 {
+    let from_sq = mv.get_from() as u16;
+    let mut to_sq = mv.get_to() as u16;
+    let flags = mv.get_flags();
+
+    if flags == MoveFlags::KING_CASTLE {
+        to_sq = from_sq + 3;
+    } else if flags == MoveFlags::QUEEN_CASTLE {
+        to_sq = from_sq - 4;
+    }
+
+    let promotion = match mv.get_promotion_piece_type() {
+        Some(PieceTypes::KNIGHT) => 1,
+        Some(PieceTypes::BISHOP) => 2,
+        Some(PieceTypes::ROOK) => 3,
+        Some(_) => 4,
+        None => 0,
+    };
+
+    (promotion << 12) | (from_sq << 6) | to_sq
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use crate::attacks::init_tables;
+
+    use super::*;
+
+    #[test]
+    fn compute_reference_polyglot_hashes_correctly() {
+        init_tables();
+        // Reference positions and keys from the Polyglot book format specification
+        for (fen, key) in [
+            (
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                0x463b96181691fc9c,
+            ),
+            (
+                "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+                0x823c9b50fd114196,
+            ),
+            (
+                "rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 2",
+                0x0756b94461c50fb0,
+            ),
+            (
+                "rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR b KQkq - 0 2",
+                0x662fafb965db29d4,
+            ),
+            (
+                "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+                0x22a48b5a8e47ff78,
+            ),
+            (
+                "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPPKPPP/RNBQ1BNR b kq - 0 3",
+                0x652a607ca3f242c1,
+            ),
+            (
+                "rnbq1bnr/ppp1pkpp/8/3pPp2/8/8/PPPPKPPP/RNBQ1BNR w - - 0 4",
+                0x00fdd303c946bdd9,
+            ),
+            (
+                "rnbqkbnr/p1pppppp/8/8/PpP4P/8/1P1PPPP1/RNBQKBNR b KQkq c3 0 3",
+                0x3c8123ea7b067637,
+            ),
+            (
+                "rnbqkbnr/p1pppppp/8/8/P6P/R1p5/1P1PPPP1/1NBQKBNR b Kkq - 0 4",
+                0x5c3f9b829b279560,
+            ),
+        ] {
+            assert_eq!(polyglot_hash(&Position::from(fen)), key, "{}", fen);
+        }
+    }
+
+    #[test]
+    fn translate_polyglot_castling_moves_correctly() {
+        init_tables();
+        let position = Position::from("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+
+        // e1h1 and e1a1
+        let king_side = decode_move(&position, (4 << 6) | 7).unwrap();
+        let queen_side = decode_move(&position, 4 << 6).unwrap();
+        assert!(king_side.get_flags() == MoveFlags::KING_CASTLE);
+        assert!(queen_side.get_flags() == MoveFlags::QUEEN_CASTLE);
+        assert!(encode_move(king_side) == (4 << 6) | 7);
+
+        // Rook moves to the king's file are not castling
+        let position = Position::from("4k3/8/8/8/8/8/8/R3K2R w - - 0 1");
+        assert!(decode_move(&position, (4 << 6) | 7).is_none());
+    }
+
+    #[test]
+    fn probe_book_entries_correctly() {
+        init_tables();
+        let start = Position::from("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+        let start_key = polyglot_hash(&start);
+        // e2e4, d2d4 and g1f3 in Polyglot encoding
+        let e2e4 = (1 << 9) | (4 << 6) | (3 << 3) | 4;
+        let d2d4 = (1 << 9) | (3 << 6) | (3 << 3) | 3;
+        let g1f3 = (6 << 6) | (2 << 3) | 5;
+
+        let mut entries = [
+            BookEntry {
+                key: start_key,
+                raw_move: e2e4,
+                weight: 10,
+                learn: 0,
+            },
+            BookEntry {
+                key: start_key,
+                raw_move: d2d4,
+                weight: 30,
+                learn: 0,
+            },
+            BookEntry {
+                key: start_key,
+                raw_move: g1f3,
+                weight: 0,
+                learn: 0,
+            },
+            BookEntry {
+                key: start_key.wrapping_sub(1),
+                raw_move: e2e4,
+                weight: 1,
+                learn: 0,
+            },
+            BookEntry {
+                key: start_key.wrapping_add(1),
+                raw_move: d2d4,
+                weight: 1,
+                learn: 0,
+            },
+            BookEntry {
+                key: 0,
+                raw_move: g1f3,
+                weight: 1,
+                learn: 0,
+            },
+        ];
+        entries.sort_by_key(|entry| entry.key);
+        let bytes: Vec<u8> = entries.iter().flat_map(|entry| entry.to_bytes()).collect();
+        let mut book = Book::new(Cursor::new(bytes)).unwrap();
+
+        assert!(book.len() == 6);
+        assert!(book.entries(&start).unwrap().len() == 3);
+        assert!(book.best_move(&start).unwrap().unwrap().to_string() == "d2d4");
+
+        let mut rng = StdRng::seed_from_u64(7);
+        for _ in 0..20 {
+            let mv = book.weighted_move(&start, &mut rng).unwrap().unwrap();
+            assert!(["e2e4", "d2d4"].contains(&mv.to_string().as_str()));
+        }
+
+        let empty = Position::from("4k3/8/8/8/8/8/8/4K3 w - - 0 1");
+        assert!(book.best_move(&empty).unwrap().is_none());
+    }
+}
@@ -1,10 +1,26 @@
 use bitflags::bitflags;
 
 bitflags! {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
     pub struct CastlingRights: u8 {
         const WHITE_KING_SIDE = 0b1000;
         const WHITE_QUEEN_SIDE = 0b0100;
         const BLACK_KING_SIDE = 0b0010;
         const BLACK_QUEEN_SIDE = 0b0001;
     }
-}
\ No newline at end of file
+}
+
+impl CastlingRights {
+    /// Returns the rights that remain after a move touches the given square
+    pub fn mask_for_square(square: u8) -> CastlingRights {
+        match square {
+            0 => !CastlingRights::WHITE_QUEEN_SIDE,
+            4 => !(CastlingRights::WHITE_KING_SIDE | CastlingRights::WHITE_QUEEN_SIDE),
+            7 => !CastlingRights::WHITE_KING_SIDE,
+            56 => !CastlingRights::BLACK_QUEEN_SIDE,
+            60 => !(CastlingRights::BLACK_KING_SIDE | CastlingRights::BLACK_QUEEN_SIDE),
+            63 => !CastlingRights::BLACK_KING_SIDE,
+            _ => CastlingRights::all(),
+        }
+    }
+}
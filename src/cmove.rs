@@ -1,6 +1,11 @@
+use std::fmt;
+
 use bitflags::bitflags;
 
+use crate::{fen::int_to_san, piece::PieceTypes};
+
 bitflags! {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
     pub struct MoveFlags: u16 {
         const QUIET = 0;
         const DOUBLE_PAWN_PUSH = 0b0001;
@@ -19,15 +24,28 @@ bitflags! {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Move(u16);
 
 impl Move {
+    /// Placeholder move that does not encode any real move
+    pub const NULL: Move = Move(0);
+
     /// Create a move with from and target square and move flags
     pub fn new(from: u16, to: u16, flags: MoveFlags) -> Self {
         Move((from) | (to << 6) | (flags.bits() << 12))
     }
 
+    /// Creates a move from its 16 bit encoding
+    pub fn from_raw(raw: u16) -> Self {
+        Move(raw)
+    }
+
+    /// Returns the 16 bit encoding of the move
+    pub fn get_raw(&self) -> u16 {
+        self.0
+    }
+
     /// Returns the from square
     pub fn get_from(&self) -> u8 {
         (self.0 & 0x3F) as u8
@@ -42,4 +60,51 @@ impl Move {
     pub fn get_flag(&self) -> u8 {
         ((self.0 >> 12) & 0xF) as u8
     }
+
+    /// Returns the move flags
+    pub fn get_flags(&self) -> MoveFlags {
+        MoveFlags::from_bits_retain(self.0 >> 12)
+    }
+
+    /// Checks if the move captures a piece, including en passant and promotion captures
+    pub fn is_capture(&self) -> bool {
+        self.get_flags().contains(MoveFlags::CAPTURE)
+    }
+
+    /// Checks if the move promotes a pawn
+    pub fn is_promotion(&self) -> bool {
+        self.get_flags().contains(MoveFlags::KNIGHT_PROM)
+    }
+
+    /// Returns the piece type a pawn promotes to
+    pub fn get_promotion_piece_type(&self) -> Option<PieceTypes> {
+        if !self.is_promotion() {
+            return None;
+        }
+        match self.get_flag() & 0b11 {
+            0 => Some(PieceTypes::KNIGHT),
+            1 => Some(PieceTypes::BISHOP),
+            2 => Some(PieceTypes::ROOK),
+            _ => Some(PieceTypes::QUEEN),
+        }
+    }
+}
+
+impl fmt::Display for Move {
+    /// Formats the move in long algebraic notation as used by UCI, e.g. `e7e8q`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}{}",
+            int_to_san(self.get_from()),
+            int_to_san(self.get_to())
+        )?;
+        match self.get_promotion_piece_type() {
+            Some(PieceTypes::KNIGHT) => write!(f, "n"),
+            Some(PieceTypes::BISHOP) => write!(f, "b"),
+            Some(PieceTypes::ROOK) => write!(f, "r"),
+            Some(_) => write!(f, "q"),
+            None => Ok(()),
+        }
+    }
 }
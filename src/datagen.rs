@@ -0,0 +1,418 @@
+//! Self-play training data generation.
+//!
+//! Games start with a few random moves and are then played by fixed node or depth searches.
+//! Every position whose best move is quiet is recorded with the search score and the final game
+//! result, both from white's point of view. Entries can be written as text lines of the form
+//! `<fen> | <score> | <result>`, which the tuner reads as well, or as packed 32 byte records:
+//!
+//! | Size     | Content                                                                   |
+//! |----------|---------------------------------------------------------------------------|
+//! | u64      | Occupancy bitboard                                                        |
+//! | 16 bytes | Piece of every occupied square in ascending order, 4 bits each, low first |
+//! | u8       | Side to move in the highest bit, en passant square or 64 below            |
+//! | u8       | Castling rights                                                           |
+//! | u8       | Halfmove clock                                                            |
+//! | u16      | Fullmove number                                                           |
+//! | i16      | Score                                                                     |
+//! | u8       | Result: 0 for a black win, 1 for a draw and 2 for a white win             |
+//!
+//! Pieces are stored as their index in `Piece::PIECE_CHARS` and numbers are little endian.
+
+use std::{
+    io::{self, BufRead, Read, Write},
+    sync::atomic::AtomicBool,
+};
+
+use rand::Rng;
+
+use crate::{
+    castling_rights::CastlingRights,
+    fen::{format_fen, parse_fen, BoardState},
+    limits::SearchLimits,
+    piece::Piece,
+    position::Position,
+    search::{is_mate_score, Search},
+    transposition_table::TranspositionTable,
+    uci::START_FEN,
+    util::{opp, Color},
+};
+
+/// Size of a packed entry in bytes
+pub const PACKED_SIZE: usize = 32;
+/// Scores beyond which a game is adjudicated as won
+const WIN_SCORE: i32 = 2500;
+/// Games still running after this many plies are adjudicated as drawn
+const MAX_GAME_PLIES: usize = 400;
+
+/// Outcome of a game
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameResult {
+    BlackWin,
+    Draw,
+    WhiteWin,
+}
+
+impl GameResult {
+    /// Returns the result as the expected score of white
+    pub fn as_f64(self) -> f64 {
+        match self {
+            GameResult::BlackWin => 0.0,
+            GameResult::Draw => 0.5,
+            GameResult::WhiteWin => 1.0,
+        }
+    }
+
+    fn win_for(color: Color) -> Self {
+        match color {
+            Color::White => GameResult::WhiteWin,
+            Color::Black => GameResult::BlackWin,
+        }
+    }
+}
+
+/// A recorded position with its score and the result of the game
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DataEntry {
+    pub fen: String,
+    /// Search score from white's point of view
+    pub score: i16,
+    pub result: GameResult,
+}
+
+impl DataEntry {
+    /// Formats the entry as a line of the text format
+    pub fn to_text(&self) -> String {
+        format!(
+            "{} | {} | {:.1}",
+            self.fen,
+            self.score,
+            self.result.as_f64()
+        )
+    }
+
+    /// Parses a line of the text format
+    pub fn from_text(line: &str) -> Result<Self, &'static str> {
+        let mut fields = line.split('|').map(str::trim);
+        let fen = fields.next().ok_or("Missing FEN")?;
+        parse_fen(fen).map_err(|_| "Invalid FEN")?;
+        let score = fields
+            .next()
+            .and_then(|score| score.parse().ok())
+            .ok_or("Invalid score")?;
+        let result = match fields.next() {
+            Some("0.0") => GameResult::BlackWin,
+            Some("0.5") => GameResult::Draw,
+            Some("1.0") => GameResult::WhiteWin,
+            _ => return Err("Invalid result"),
+        };
+        Ok(DataEntry {
+            fen: fen.to_string(),
+            score,
+            result,
+        })
+    }
+
+    /// Packs the entry into the binary format
+    pub fn to_packed(&self) -> [u8; PACKED_SIZE] {
+        let BoardState(mailbox, color, castling, en_passant, halfmove, fullmove) =
+            parse_fen(&self.fen).expect("entries hold valid FENs");
+
+        let mut bytes = [0; PACKED_SIZE];
+        let mut occupancy = 0u64;
+        let mut nibble = 0;
+        for (square, piece) in mailbox.iter().enumerate() {
+            if let Some(piece) = piece {
+                occupancy |= 1 << square;
+                bytes[8 + nibble / 2] |= (piece.get_index() as u8) << (4 * (nibble % 2));
+                nibble += 1;
+            }
+        }
+        bytes[..8].copy_from_slice(&occupancy.to_le_bytes());
+        bytes[24] = (color as u8) << 7 | en_passant.unwrap_or(64);
+        bytes[25] = castling.bits();
+        bytes[26] = halfmove.min(u8::MAX as usize) as u8;
+        bytes[27..29].copy_from_slice(&(fullmove.min(u16::MAX as usize) as u16).to_le_bytes());
+        bytes[29..31].copy_from_slice(&self.score.to_le_bytes());
+        bytes[31] = self.result as u8;
+        bytes
+    }
+
+    /// Unpacks an entry of the binary format
+    pub fn from_packed(bytes: &[u8; PACKED_SIZE]) -> Result<Self, &'static str> {
+        let occupancy = u64::from_le_bytes(bytes[..8].try_into().expect("8 bytes"));
+        if occupancy.count_ones() > 32 {
+            return Err("Too many pieces");
+        }
+
+        let mut mailbox = [None; 64];
+        let mut nibble = 0;
+        for (square, piece) in mailbox.iter_mut().enumerate() {
+            if occupancy & (1 << square) != 0 {
+                let idx = (bytes[8 + nibble / 2] >> (4 * (nibble % 2))) & 0xf;
+                let ch = Piece::PIECE_CHARS
+                    .get(idx as usize)
+                    .ok_or("Invalid piece")?;
+                *piece = Piece::from_char(*ch);
+                nibble += 1;
+            }
+        }
+
+        let color = if bytes[24] >> 7 == 0 {
+            Color::White
+        } else {
+            Color::Black
+        };
+        let en_passant = match bytes[24] & 0x7f {
+            64 => None,
+            square if square < 64 => Some(square),
+            _ => return Err("Invalid en passant square"),
+        };
+        let castling = CastlingRights::from_bits(bytes[25]).ok_or("Invalid castling rights")?;
+        let result = match bytes[31] {
+            0 => GameResult::BlackWin,
+            1 => GameResult::Draw,
+            2 => GameResult::WhiteWin,
+            _ => return Err("Invalid result"),
+        };
+
+        let board_state = BoardState(
+            mailbox,
+            color,
+            castling,
+            en_passant,
+            bytes[26] as usize,
+            u16::from_le_bytes([bytes[27], bytes[28]]) as usize,
+        );
+        // The board itself is validated by the FEN parser, e.g. for the number of kings
+        let fen = format_fen(&board_state);
+        parse_fen(&fen).map_err(|_| "Invalid position")?;
+        Ok(DataEntry {
+            fen,
+            score: i16::from_le_bytes([bytes[29], bytes[30]]),
+            result,
+        })
+    }
+}
+
+/// Writes entries in the packed binary format
+pub fn write_packed<W: Write>(writer: &mut W, entries: &[DataEntry]) -> io::Result<()> {
+    for entry in entries {
+        writer.write_all(&entry.to_packed())?;
+    }
+    Ok(())
+}
+
+/// Reads all entries of the packed binary format
+pub fn read_packed<R: Read>(mut reader: R) -> io::Result<Vec<DataEntry>> {
+    let invalid = |msg| io::Error::new(io::ErrorKind::InvalidData, msg);
+    let mut entries = Vec::new();
+    let mut bytes = [0; PACKED_SIZE];
+    loop {
+        let mut filled = 0;
+        while filled < PACKED_SIZE {
+            match reader.read(&mut bytes[filled..]) {
+                Ok(0) => break,
+                Ok(len) => filled += len,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => (),
+                Err(err) => return Err(err),
+            }
+        }
+        match filled {
+            0 => return Ok(entries),
+            PACKED_SIZE => entries.push(DataEntry::from_packed(&bytes).map_err(invalid)?),
+            _ => return Err(invalid("Truncated entry")),
+        }
+    }
+}
+
+/// Writes entries in the text format, one per line
+pub fn write_text<W: Write>(writer: &mut W, entries: &[DataEntry]) -> io::Result<()> {
+    for entry in entries {
+        writeln!(writer, "{}", entry.to_text())?;
+    }
+    Ok(())
+}
+
+/// Reads all entries of the text format, skipping empty lines
+pub fn read_text<R: BufRead>(reader: R) -> io::Result<Vec<DataEntry>> {
+    let mut entries = Vec::new();
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        entries.push(
+            DataEntry::from_text(&line)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?,
+        );
+    }
+    Ok(entries)
+}
+
+/// Settings of the self-play games
+#[derive(Debug, Clone)]
+pub struct DatagenConfig {
+    /// Limits of the search for every move, usually a node or depth limit
+    pub limits: SearchLimits,
+    /// Number of random moves played from the starting position
+    pub random_plies: usize,
+}
+
+/// Plays a self-play game and returns its quiet positions labelled with the result
+pub fn play_game<G: Rng>(
+    config: &DatagenConfig,
+    tt: &TranspositionTable,
+    rng: &mut G,
+) -> Vec<DataEntry> {
+    let mut position = random_opening(config.random_plies, rng);
+    tt.clear();
+    let stop = AtomicBool::new(false);
+
+    let mut recorded = Vec::new();
+    let mut history = None;
+    let mut plies = 0;
+    let result = loop {
+        let color = position.side_to_move();
+        if position.generate_moves().is_empty() {
+            break if position.in_check() {
+                GameResult::win_for(opp(color))
+            } else {
+                GameResult::Draw
+            };
+        }
+        if position.is_draw() || plies >= MAX_GAME_PLIES {
+            break GameResult::Draw;
+        }
+
+        tt.new_search();
+        let mut search = Search::new(position.clone(), config.limits.clone(), &stop, tt);
+        search.set_print_info(false);
+        if let Some(history) = history.take() {
+            search.set_history(history);
+        }
+        let search_result = search.run();
+        history = Some(search.into_history());
+        let score = search_result.score;
+
+        if score.abs() >= WIN_SCORE {
+            break if score > 0 {
+                GameResult::win_for(color)
+            } else {
+                GameResult::win_for(opp(color))
+            };
+        }
+
+        // Positions in check or with a tactical best move do not tell much about the evaluation
+        let mv = search_result.best_move;
+        if !position.in_check() && !mv.is_capture() && !mv.is_promotion() && !is_mate_score(score) {
+            let white_score = match color {
+                Color::White => score,
+                Color::Black => -score,
+            };
+            recorded.push((position.to_fen(), white_score as i16));
+        }
+
+        position.make_move(mv);
+        plies += 1;
+    };
+
+    recorded
+        .into_iter()
+        .map(|(fen, score)| DataEntry { fen, score, result })
+        .collect()
+}
+
+/// Plays random legal moves from the starting position, starting over if the game ends
+fn random_opening<G: Rng>(plies: usize, rng: &mut G) -> Position {
+    'restart: loop {
+        let mut position = Position::from(START_FEN);
+        for _ in 0..plies {
+            let moves = position.generate_moves();
+            if moves.is_empty() {
+                continue 'restart;
+            }
+            position.make_move(moves[rng.gen_range(0..moves.len())]);
+        }
+        if position.generate_moves().is_empty() {
+            continue;
+        }
+        return position;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use crate::attacks::init_tables;
+
+    use super::*;
+
+    #[test]
+    fn round_trip_both_formats() {
+        init_tables();
+        let entries = vec![
+            DataEntry {
+                fen: String::from(START_FEN),
+                score: 25,
+                result: GameResult::Draw,
+            },
+            DataEntry {
+                fen: String::from("r3k2r/8/8/3pP3/8/8/8/R3K2R w Kq d6 12 40"),
+                score: -1234,
+                result: GameResult::BlackWin,
+            },
+            DataEntry {
+                fen: String::from("8/2P5/8/8/8/8/k7/2K5 b - - 0 70"),
+                score: 900,
+                result: GameResult::WhiteWin,
+            },
+        ];
+
+        let mut packed = Vec::new();
+        write_packed(&mut packed, &entries).unwrap();
+        assert!(packed.len() == entries.len() * PACKED_SIZE);
+        assert!(read_packed(packed.as_slice()).unwrap() == entries);
+
+        let mut text = Vec::new();
+        write_text(&mut text, &entries).unwrap();
+        assert!(read_text(text.as_slice()).unwrap() == entries);
+        assert!(entries[1].to_text() == "r3k2r/8/8/3pP3/8/8/8/R3K2R w Kq d6 12 40 | -1234 | 0.0");
+
+        // Truncated records are not silently dropped
+        assert!(read_packed(&packed[..PACKED_SIZE + 1]).is_err());
+        packed[31] = 7;
+        assert!(read_packed(packed.as_slice()).is_err());
+
+        // Boards without kings or with two kings of a side are rejected
+        let mut bytes = entries[0].to_packed();
+        bytes[..8].fill(0);
+        assert!(DataEntry::from_packed(&bytes) == Err("Invalid position"));
+        let king = Piece::from_char('K').unwrap().get_index() as u8;
+        bytes[..8].copy_from_slice(&0b11u64.to_le_bytes());
+        bytes[8] = king << 4 | king;
+        assert!(DataEntry::from_packed(&bytes) == Err("Invalid position"));
+    }
+
+    #[test]
+    fn record_quiet_positions_of_self_play_games() {
+        init_tables();
+        let config = DatagenConfig {
+            limits: SearchLimits {
+                depth: Some(2),
+                ..SearchLimits::default()
+            },
+            random_plies: 8,
+        };
+        let tt = TranspositionTable::new(1);
+        let entries = play_game(&config, &tt, &mut StdRng::seed_from_u64(5));
+        assert!(!entries.is_empty());
+
+        let result = entries[0].result;
+        for entry in &entries {
+            assert!(entry.result == result);
+            let position = Position::from(entry.fen.as_str());
+            assert!(!position.in_check());
+        }
+    }
+}
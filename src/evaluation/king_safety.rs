@@ -0,0 +1,172 @@
+use super::{params::EvalParams, S};
+use crate::{
+    attacks::{lookup_king_att, lookup_piece_att},
+    piece::PieceTypes,
+    position::Position,
+    util::{
+        bb_from_square, enumerate_bits, mask_rank, north_fill, opp, relative_rank_of, south_fill,
+        sq_to_file, Bitboard, Color, FILES,
+    },
+};
+
+/// Attack units per attacked king zone square, indexed by piece type
+pub const KING_ATTACK_WEIGHTS: [i32; 6] = [0, 2, 2, 3, 5, 0];
+/// Upper bound of the king danger penalty
+pub const MAX_KING_DANGER: i32 = 600;
+/// Bonus for the closest own pawn in front of the king on the king's and adjacent files, by its
+/// relative rank. Index 0 is used for a file without such a pawn.
+pub const SHELTER: [S; 8] = [
+    S(-25, 0),
+    S(20, 0),
+    S(12, 0),
+    S(4, 0),
+    S(0, 0),
+    S(0, 0),
+    S(0, 0),
+    S(0, 0),
+];
+/// Penalty for the closest enemy pawn in front of the king on the king's and adjacent files, by
+/// its rank relative to the king's side. Index 0 is used for a file without such a pawn.
+pub const STORM: [S; 8] = [
+    S(0, 0),
+    S(0, 0),
+    S(-30, 0),
+    S(-20, 0),
+    S(-10, 0),
+    S(-5, 0),
+    S(0, 0),
+    S(0, 0),
+];
+/// Penalty for a file next to the king without own pawns
+pub const SEMI_OPEN_FILE_NEAR_KING: S = S(-10, 0);
+/// Penalty for a file next to the king without any pawns
+pub const OPEN_FILE_NEAR_KING: S = S(-20, 0);
+
+/// Evaluates the king safety of both sides from white's point of view
+pub fn evaluate_king_safety(position: &Position, params: &EvalParams) -> S {
+    king_safety(position, Color::White, params) - king_safety(position, Color::Black, params)
+}
+
+pub(super) fn king_safety(position: &Position, color: Color, params: &EvalParams) -> S {
+    let king_sq = position.king_square(color);
+    king_danger(position, color, king_sq, params) + pawn_shield(position, color, king_sq, params)
+}
+
+/// Penalizes enemy pieces attacking the squares around the king, growing quadratically with the
+/// attack units so coordinated attacks weigh more than single attackers
+fn king_danger(position: &Position, color: Color, king_sq: u8, params: &EvalParams) -> S {
+    let enemy = opp(color);
+    let zone = lookup_king_att(king_sq) | bb_from_square(king_sq);
+    let occupancy = position.all_pieces_bb(None);
+
+    let mut attackers = 0;
+    let mut units = 0;
+    for piece_type in [
+        PieceTypes::KNIGHT,
+        PieceTypes::BISHOP,
+        PieceTypes::ROOK,
+        PieceTypes::QUEEN,
+    ] {
+        enumerate_bits(position.piece_bb(enemy, piece_type), |square| {
+            let attacks = lookup_piece_att(piece_type, square, enemy, occupancy) & zone;
+            if attacks != 0 {
+                attackers += 1;
+                units += params.king_attack_weights[piece_type.bits() as usize]
+                    * attacks.count_ones() as i32;
+            }
+        });
+    }
+
+    // A single attacker rarely mates on its own
+    if attackers < 2 {
+        return S::default();
+    }
+    let danger = (units * units / 4).min(params.max_king_danger);
+    S(-danger, -danger / 4)
+}
+
+/// Scores the pawns sheltering the king, enemy pawns storming it and open files around it
+fn pawn_shield(position: &Position, color: Color, king_sq: u8, params: &EvalParams) -> S {
+    let own_pawns = position.piece_bb(color, PieceTypes::PAWN);
+    let enemy_pawns = position.piece_bb(opp(color), PieceTypes::PAWN);
+    // Ranks from the king's rank towards the enemy
+    let in_front = match color {
+        Color::White => north_fill(mask_rank(king_sq)),
+        Color::Black => south_fill(mask_rank(king_sq)),
+    };
+
+    let mut score = S::default();
+    let center_file = sq_to_file(king_sq).clamp(1, 6) as usize;
+    for &file in &FILES[center_file - 1..=center_file + 1] {
+        let own = own_pawns & file & in_front;
+        let enemy = enemy_pawns & file & in_front;
+
+        score += params.shelter[closest_rank(own, color)];
+        score += params.storm[closest_rank(enemy, color)];
+
+        if own_pawns & file == 0 {
+            score += if enemy_pawns & file == 0 {
+                params.open_file_near_king
+            } else {
+                params.semi_open_file_near_king
+            };
+        }
+    }
+    score
+}
+
+/// Returns the relative rank of the pawn closest to the king's side of the board, or 0
+fn closest_rank(pawns: Bitboard, color: Color) -> usize {
+    if pawns == 0 {
+        return 0;
+    }
+    let square = match color {
+        Color::White => pawns.trailing_zeros(),
+        Color::Black => 63 - pawns.leading_zeros(),
+    };
+    relative_rank_of(square as u8, color) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{attacks::init_tables, evaluation::params::DEFAULT_PARAMS};
+
+    use super::*;
+
+    fn safety(fen: &str, color: Color) -> S {
+        king_safety(&Position::from(fen), color, &DEFAULT_PARAMS)
+    }
+
+    #[test]
+    fn penalize_coordinated_attacks() {
+        init_tables();
+        // Queen and knight both hit the squares around the castled king
+        let attacked = safety("6k1/8/8/8/8/5n1q/5PPP/6K1 w - - 0 1", Color::White);
+        let defended = safety("6k1/8/8/8/8/8/5PPP/6K1 w - - 0 1", Color::White);
+        assert!(defended.0 - attacked.0 > 40);
+        assert!(defended.1 > attacked.1);
+
+        // A lone attacker is not counted
+        let single = safety("6k1/8/8/8/6q1/8/5PPP/6K1 w - - 0 1", Color::White);
+        assert!(single == defended);
+    }
+
+    #[test]
+    fn score_pawn_shelter_and_storms() {
+        init_tables();
+        let full = safety("6k1/8/8/8/8/8/5PPP/6K1 w - - 0 1", Color::White);
+        assert!(full == SHELTER[1] * 3);
+
+        // An advanced g-pawn shelters less, a missing h-pawn leaves an open file
+        let advanced = safety("6k1/8/8/8/8/6P1/5P2/6K1 w - - 0 1", Color::White);
+        assert!(advanced == SHELTER[1] + SHELTER[2] + SHELTER[0] + OPEN_FILE_NEAR_KING);
+
+        // Enemy pawns approaching the king
+        let stormed = safety("6k1/8/8/8/7p/6p1/5PPP/6K1 w - - 0 1", Color::White);
+        assert!(stormed == full + STORM[2] + STORM[3]);
+
+        // The same structure seen from black
+        let black = safety("6k1/5ppp/6P1/7P/8/8/8/6K1 b - - 0 1", Color::Black);
+        assert!(black == stormed);
+    }
+}
@@ -0,0 +1,272 @@
+use super::{params::EvalParams, S};
+use crate::{
+    attacks::lookup_piece_att,
+    piece::PieceTypes,
+    position::Position,
+    util::{
+        bb_from_square, enumerate_bits, front_span, mask_file, mask_rank, opp, pawn_attacks_bb,
+        relative_rank_of, shift_east, shift_west, sq_to_file, square_distance, Bitboard, Color,
+    },
+};
+
+/// Score per safe square a piece attacks beyond its baseline, indexed by piece type
+pub const MOBILITY: [S; 6] = [S(0, 0), S(4, 4), S(5, 5), S(2, 4), S(1, 2), S(0, 0)];
+/// Number of safe squares for which a piece neither gains nor loses mobility score
+pub const MOBILITY_BASELINE: [i32; 6] = [0, 4, 6, 7, 13, 0];
+/// Bonus for having both bishops
+pub const BISHOP_PAIR: S = S(30, 50);
+/// Bonus for a rook on a file without pawns
+pub const ROOK_OPEN_FILE: S = S(25, 10);
+/// Bonus for a rook on a file without own pawns
+pub const ROOK_SEMI_OPEN_FILE: S = S(12, 5);
+/// Bonus for a rook on the seventh rank confining the king or attacking pawns
+pub const ROOK_ON_SEVENTH: S = S(10, 20);
+/// Bonus for a knight supported by a pawn in enemy territory that no enemy pawn can chase away
+pub const KNIGHT_OUTPOST: S = S(20, 10);
+/// Penalty for a rook locked in the corner by its own uncastled king
+pub const TRAPPED_ROOK: S = S(-40, -10);
+/// Penalty for a bishop cut off on a7 or h7 by an enemy pawn on b6 or g6
+pub const TRAPPED_BISHOP: S = S(-80, -80);
+
+/// Evaluates mobility and piece activity of both sides from white's point of view
+pub fn evaluate_mobility(position: &Position, params: &EvalParams) -> S {
+    mobility(position, Color::White, params) + piece_activity(position, Color::White, params)
+        - mobility(position, Color::Black, params)
+        - piece_activity(position, Color::Black, params)
+}
+
+/// Returns the squares pieces of the given color can move to without being taken by a pawn
+fn mobility_area(position: &Position, color: Color) -> Bitboard {
+    let enemy = opp(color);
+    let enemy_pawn_attacks = pawn_attacks_bb(position.piece_bb(enemy, PieceTypes::PAWN), enemy);
+    !position.all_pieces_bb(color.into()) & !enemy_pawn_attacks
+}
+
+/// Scores the number of safe squares each piece attacks
+pub(super) fn mobility(position: &Position, color: Color, params: &EvalParams) -> S {
+    let occupancy = position.all_pieces_bb(None);
+    let area = mobility_area(position, color);
+
+    let mut score = S::default();
+    for piece_type in [
+        PieceTypes::KNIGHT,
+        PieceTypes::BISHOP,
+        PieceTypes::ROOK,
+        PieceTypes::QUEEN,
+    ] {
+        let idx = piece_type.bits() as usize;
+        enumerate_bits(position.piece_bb(color, piece_type), |square| {
+            let attacks = lookup_piece_att(piece_type, square, color, occupancy);
+            let count = (attacks & area).count_ones() as i32;
+            score += params.mobility[idx] * (count - MOBILITY_BASELINE[idx]);
+        });
+    }
+    score
+}
+
+/// Scores the bishop pair, rook files, knight outposts and trapped pieces
+pub(super) fn piece_activity(position: &Position, color: Color, params: &EvalParams) -> S {
+    let own_pawns = position.piece_bb(color, PieceTypes::PAWN);
+    let enemy_pawns = position.piece_bb(opp(color), PieceTypes::PAWN);
+
+    let mut score = S::default();
+    if position.piece_bb(color, PieceTypes::BISHOP).count_ones() >= 2 {
+        score += params.bishop_pair;
+    }
+    score += rooks(
+        position,
+        color,
+        own_pawns,
+        enemy_pawns,
+        mobility_area(position, color),
+        params,
+    );
+    score += knight_outposts(position, color, own_pawns, enemy_pawns, params);
+    score += trapped_bishops(position, color, enemy_pawns, params);
+    score
+}
+
+fn rooks(
+    position: &Position,
+    color: Color,
+    own_pawns: Bitboard,
+    enemy_pawns: Bitboard,
+    mobility_area: Bitboard,
+    params: &EvalParams,
+) -> S {
+    let enemy_king = position.king_square(opp(color));
+    let own_king = position.king_square(color);
+    let occupancy = position.all_pieces_bb(None);
+    let mut score = S::default();
+
+    enumerate_bits(position.piece_bb(color, PieceTypes::ROOK), |square| {
+        let file = mask_file(square);
+        if own_pawns & file == 0 {
+            score += if enemy_pawns & file == 0 {
+                params.rook_open_file
+            } else {
+                params.rook_semi_open_file
+            };
+        }
+
+        if relative_rank_of(square, color) == 6
+            && (relative_rank_of(enemy_king, color) == 7 || enemy_pawns & mask_rank(square) != 0)
+        {
+            score += params.rook_on_seventh;
+        }
+
+        // A rook next to its king on the back rank, with the king between it and the center. A
+        // king still in the center may castle and free the rook.
+        let moves = lookup_piece_att(PieceTypes::ROOK, square, color, occupancy) & mobility_area;
+        let king_file = sq_to_file(own_king);
+        let rook_file = sq_to_file(square);
+        let locked_in = (matches!(king_file, 5 | 6) && rook_file > king_file)
+            || (matches!(king_file, 1 | 2) && rook_file < king_file);
+        if relative_rank_of(own_king, color) == 0
+            && relative_rank_of(square, color) <= 1
+            && square_distance(own_king, square) <= 3
+            && locked_in
+            && moves.count_ones() <= 3
+        {
+            score += params.trapped_rook;
+        }
+    });
+    score
+}
+
+fn knight_outposts(
+    position: &Position,
+    color: Color,
+    own_pawns: Bitboard,
+    enemy_pawns: Bitboard,
+    params: &EvalParams,
+) -> S {
+    let supported = pawn_attacks_bb(own_pawns, color);
+    // Squares enemy pawns attack now or after advancing
+    let enemy_attacks = pawn_attacks_bb(enemy_pawns, opp(color));
+    let enemy_attack_span = enemy_attacks | front_span(enemy_attacks, opp(color));
+
+    let mut score = S::default();
+    enumerate_bits(position.piece_bb(color, PieceTypes::KNIGHT), |square| {
+        let rank = relative_rank_of(square, color);
+        let bb = bb_from_square(square);
+        if (3..=5).contains(&rank) && bb & supported != 0 && bb & enemy_attack_span == 0 {
+            score += params.knight_outpost;
+        }
+    });
+    score
+}
+
+fn trapped_bishops(
+    position: &Position,
+    color: Color,
+    enemy_pawns: Bitboard,
+    params: &EvalParams,
+) -> S {
+    let bishops = position.piece_bb(color, PieceTypes::BISHOP);
+    let mut score = S::default();
+
+    enumerate_bits(bishops, |square| {
+        if relative_rank_of(square, color) != 6 {
+            return;
+        }
+        // The pawn one rank below and one file towards the center blocks the way out
+        let bb = bb_from_square(square);
+        let blocker = match color {
+            Color::White => bb >> 8,
+            Color::Black => bb << 8,
+        };
+        let blocker = match sq_to_file(square) {
+            0 => shift_east(blocker),
+            7 => shift_west(blocker),
+            _ => 0,
+        };
+        if blocker & enemy_pawns != 0 {
+            score += params.trapped_bishop;
+        }
+    });
+    score
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{attacks::init_tables, evaluation::params::DEFAULT_PARAMS};
+
+    use super::*;
+
+    fn activity(fen: &str, color: Color) -> S {
+        activity_with(fen, color, &DEFAULT_PARAMS)
+    }
+
+    fn activity_with(fen: &str, color: Color, params: &EvalParams) -> S {
+        let position = Position::from(fen);
+        mobility(&position, color, params) + piece_activity(&position, color, params)
+    }
+
+    #[test]
+    fn count_safe_squares_only() {
+        init_tables();
+        // The knight on d4 reaches 8 squares, b5 is covered by the black pawn on c6
+        let position = Position::from("4k3/8/2p5/8/3N4/8/8/4K3 w - - 0 1");
+        let knight = activity("4k3/8/2p5/8/3N4/8/8/4K3 w - - 0 1", Color::White);
+        assert!(knight == MOBILITY[1] * (7 - MOBILITY_BASELINE[1]));
+
+        // Own pieces block the bishop and take away its squares
+        let blocked = activity("4k3/8/8/8/8/8/1P1P4/2B1K3 w - - 0 1", Color::White);
+        assert!(blocked == MOBILITY[2] * (0 - MOBILITY_BASELINE[2]));
+        assert!(evaluate_mobility(&position, &DEFAULT_PARAMS) == knight);
+    }
+
+    #[test]
+    fn score_piece_activity_patterns() {
+        init_tables();
+        let pair = activity("4k3/8/8/8/8/8/8/2B1KB2 w - - 0 1", Color::White);
+        let single = activity("4k3/8/8/8/8/8/8/2B1K3 w - - 0 1", Color::White);
+        assert!(pair - single == BISHOP_PAIR + MOBILITY[2] * (7 - MOBILITY_BASELINE[2]));
+
+        // Open, semi-open and closed files
+        let open = activity("4k3/8/8/8/8/8/8/R3K3 w - - 0 1", Color::White);
+        let semi_open = activity("4k3/p7/8/8/8/8/8/R3K3 w - - 0 1", Color::White);
+        let closed = activity("4k3/p7/8/8/8/8/P7/R3K3 w - - 0 1", Color::White);
+        assert!(open - semi_open == ROOK_OPEN_FILE - ROOK_SEMI_OPEN_FILE + MOBILITY[3]);
+        assert!(semi_open.0 > closed.0);
+
+        // Rook on the seventh rank against the king on the eighth
+        let seventh = activity("4k3/R7/8/8/8/8/8/4K3 w - - 0 1", Color::White);
+        let sixth = activity("4k3/8/R7/8/8/8/8/4K3 w - - 0 1", Color::White);
+        assert!(seventh - sixth == ROOK_ON_SEVENTH);
+    }
+
+    #[test]
+    fn detect_outposts_and_trapped_pieces() {
+        init_tables();
+        // The e5 knight is supported by d4 and no black pawn can attack it. A pawn on f7 could,
+        // and it also takes g6 away from the knight.
+        let outpost = activity("4k3/8/8/4N3/3P4/8/8/4K3 w - - 0 1", Color::White);
+        let chased = activity("4k3/5p2/8/4N3/3P4/8/8/4K3 w - - 0 1", Color::White);
+        assert!(outpost - chased == KNIGHT_OUTPOST + MOBILITY[1]);
+
+        let trapped = activity("4k3/B7/1p6/8/8/8/8/4K3 w - - 0 1", Color::White);
+        let free = activity("4k3/B7/8/8/8/8/8/4K3 w - - 0 1", Color::White);
+        assert!((trapped - free).0 <= TRAPPED_BISHOP.0);
+
+        let without_trapped_rook = EvalParams {
+            trapped_rook: S::default(),
+            ..DEFAULT_PARAMS.clone()
+        };
+        let locked = "4k3/8/8/8/8/8/5PPP/5KR1 w - - 0 1";
+        assert!(
+            activity(locked, Color::White)
+                == activity_with(locked, Color::White, &without_trapped_rook) + TRAPPED_ROOK
+        );
+        let castled = activity("4k3/8/8/8/8/8/5PPP/5RK1 w - - 0 1", Color::White);
+        assert!(castled.0 > activity(locked, Color::White).0);
+
+        // A king in the center may still castle, its rook is not trapped
+        let uncastled = "4k3/8/8/8/8/8/4PPPP/4K2R w - - 0 1";
+        assert!(
+            activity(uncastled, Color::White)
+                == activity_with(uncastled, Color::White, &without_trapped_rook)
+        );
+    }
+}
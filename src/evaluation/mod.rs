@@ -0,0 +1,294 @@
+pub mod king_safety;
+pub mod mobility;
+pub mod params;
+pub mod pawns;
+pub mod psqt;
+pub mod trace;
+
+use std::ops::{Add, AddAssign, Mul, Neg, Sub, SubAssign};
+
+use crate::{
+    position::Position,
+    util::{enumerate_bits, Color},
+};
+
+use self::{
+    king_safety::evaluate_king_safety,
+    mobility::evaluate_mobility,
+    params::{EvalParams, DEFAULT_PARAMS},
+    pawns::{evaluate_passers, evaluate_pawn_structure, PawnEntry, PawnTable},
+};
+
+/// Game phase weight of each piece type, the starting position has the maximum phase
+pub const PHASE_WEIGHTS: [i32; 6] = [0, 1, 1, 2, 4, 0];
+/// Phase of the starting position, from which on the middlegame score is used exclusively
+pub const MAX_PHASE: i32 = 24;
+
+/// A pair of middlegame and endgame scores
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct S(pub i32, pub i32);
+
+impl S {
+    /// Interpolates between the middlegame and the endgame score by game phase
+    pub fn taper(self, phase: i32) -> i32 {
+        (self.0 * phase + self.1 * (MAX_PHASE - phase)) / MAX_PHASE
+    }
+}
+
+impl Add for S {
+    type Output = S;
+
+    fn add(self, rhs: S) -> S {
+        S(self.0 + rhs.0, self.1 + rhs.1)
+    }
+}
+
+impl AddAssign for S {
+    fn add_assign(&mut self, rhs: S) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sub for S {
+    type Output = S;
+
+    fn sub(self, rhs: S) -> S {
+        S(self.0 - rhs.0, self.1 - rhs.1)
+    }
+}
+
+impl SubAssign for S {
+    fn sub_assign(&mut self, rhs: S) {
+        *self = *self - rhs;
+    }
+}
+
+impl Neg for S {
+    type Output = S;
+
+    fn neg(self) -> S {
+        S(-self.0, -self.1)
+    }
+}
+
+impl Mul<i32> for S {
+    type Output = S;
+
+    fn mul(self, rhs: i32) -> S {
+        S(self.0 * rhs, self.1 * rhs)
+    }
+}
+
+/// Evaluates positions, caching the pawn structure evaluation between calls
+#[derive(Default)]
+pub struct Evaluator {
+    pawn_table: PawnTable,
+    params: EvalParams,
+}
+
+impl Evaluator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_params(params: EvalParams) -> Self {
+        Evaluator {
+            pawn_table: PawnTable::new(),
+            params,
+        }
+    }
+
+    /// Evaluates the position from the point of view of the side to move, with the network if
+    /// the position keeps its accumulators
+    pub fn evaluate(&mut self, position: &Position) -> i32 {
+        if let Some(nnue) = position.nnue() {
+            return nnue.evaluate(position.side_to_move());
+        }
+        let pawns = self.pawn_table.probe(position, &self.params);
+        evaluate_with_pawns(position, &pawns, &self.params)
+    }
+}
+
+/// Evaluates the position from the point of view of the side to move without any caching
+pub fn evaluate(position: &Position) -> i32 {
+    evaluate_with_params(position, &DEFAULT_PARAMS)
+}
+
+/// Evaluates the position from the point of view of the side to move with the given weights
+pub fn evaluate_with_params(position: &Position, params: &EvalParams) -> i32 {
+    let pawns = evaluate_pawn_structure(position, params);
+    evaluate_with_pawns(position, &pawns, params)
+}
+
+fn evaluate_with_pawns(position: &Position, pawns: &PawnEntry, params: &EvalParams) -> i32 {
+    let score = material_and_psqt(position, params)
+        + pawns.score
+        + evaluate_passers(position, pawns.passed, params)
+        + evaluate_king_safety(position, params)
+        + evaluate_mobility(position, params);
+    let score = score.taper(game_phase(position));
+
+    match position.side_to_move() {
+        Color::White => score,
+        Color::Black => -score,
+    }
+}
+
+/// Returns the game phase from `MAX_PHASE` in the opening down to 0 with only pawns and kings
+pub fn game_phase(position: &Position) -> i32 {
+    let phase: i32 = position
+        .pieces
+        .iter()
+        .enumerate()
+        .map(|(idx, bb)| PHASE_WEIGHTS[idx % 6] * bb.count_ones() as i32)
+        .sum();
+    // Promotions may push the phase beyond the starting position
+    phase.min(MAX_PHASE)
+}
+
+/// Sums material and piece-square scores from white's point of view
+pub fn material_and_psqt(position: &Position, params: &EvalParams) -> S {
+    material(position, Color::White, params) + piece_squares(position, Color::White, params)
+        - material(position, Color::Black, params)
+        - piece_squares(position, Color::Black, params)
+}
+
+fn material(position: &Position, color: Color, params: &EvalParams) -> S {
+    let mut score = S::default();
+    // The pieces are indexed by color and piece type, white pieces first
+    for (piece_type, bb) in position.pieces[color as usize * 6..][..6]
+        .iter()
+        .enumerate()
+    {
+        score += params.material[piece_type] * bb.count_ones() as i32;
+    }
+    score
+}
+
+fn piece_squares(position: &Position, color: Color, params: &EvalParams) -> S {
+    let mut score = S::default();
+    for (piece_type, bb) in position.pieces[color as usize * 6..][..6]
+        .iter()
+        .enumerate()
+    {
+        enumerate_bits(*bb, |square| {
+            score += params.psqt[piece_type][psqt_index(square, color)];
+        });
+    }
+    score
+}
+
+/// Maps a square to the piece-square table index, which is laid out from white's point of view
+/// with a8 first. Black pieces use the table mirrored vertically.
+pub fn psqt_index(square: u8, color: Color) -> usize {
+    match color {
+        Color::White => (square ^ 56) as usize,
+        Color::Black => square as usize,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::attacks::init_tables;
+
+    use super::*;
+
+    /// Flips the board vertically and swaps the colors of all pieces and the side to move
+    pub fn mirror_fen(fen: &str) -> String {
+        let fields: Vec<&str> = fen.split(' ').collect();
+        let swap_case = |s: &str| -> String {
+            s.chars()
+                .map(|ch| {
+                    if ch.is_ascii_uppercase() {
+                        ch.to_ascii_lowercase()
+                    } else {
+                        ch.to_ascii_uppercase()
+                    }
+                })
+                .collect()
+        };
+
+        let board: Vec<String> = fields[0].split('/').rev().map(swap_case).collect();
+        let side = if fields[1] == "w" { "b" } else { "w" };
+        let castling = if fields[2] == "-" {
+            String::from("-")
+        } else {
+            let mut rights: Vec<char> = swap_case(fields[2]).chars().collect();
+            rights.sort_by_key(|ch| "KQkq".find(*ch));
+            rights.into_iter().collect()
+        };
+        let en_passant = match fields[3] {
+            "-" => String::from("-"),
+            square => {
+                let rank = square.as_bytes()[1] - b'0';
+                format!("{}{}", &square[..1], 9 - rank)
+            }
+        };
+        format!(
+            "{} {} {} {} {} {}",
+            board.join("/"),
+            side,
+            castling,
+            en_passant,
+            fields[4],
+            fields[5]
+        )
+    }
+
+    #[test]
+    fn evaluate_symmetrically() {
+        init_tables();
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        assert!(evaluate(&Position::from(start)) == 0);
+
+        for fen in [
+            "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        ] {
+            let position = Position::from(fen);
+            let mirrored = Position::from(mirror_fen(fen).as_str());
+            assert!(evaluate(&position) == evaluate(&mirrored));
+        }
+    }
+
+    #[test]
+    fn taper_by_game_phase() {
+        init_tables();
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        assert!(game_phase(&Position::from(start)) == MAX_PHASE);
+        assert!(game_phase(&Position::from("4k3/4p3/8/8/8/8/4P3/4K3 w - - 0 1")) == 0);
+        assert!(game_phase(&Position::from("3qk3/8/8/8/8/8/8/R3K3 w - - 0 1")) == 6);
+
+        assert!(S(100, 200).taper(MAX_PHASE) == 100);
+        assert!(S(100, 200).taper(0) == 200);
+        assert!(S(100, 200).taper(MAX_PHASE / 2) == 150);
+
+        // The king belongs in the center in the endgame
+        let center = evaluate(&Position::from("8/8/8/3k4/8/8/8/K7 b - - 0 1"));
+        assert!(center > 0);
+    }
+
+    #[test]
+    fn evaluate_identically_with_pawn_cache() {
+        init_tables();
+        let mut evaluator = Evaluator::new();
+        let mut position =
+            Position::from("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1");
+        for &mv in position.clone().generate_moves().iter() {
+            position.make_move(mv);
+            assert!(evaluator.evaluate(&position) == evaluate(&position));
+            assert!(evaluator.evaluate(&position) == evaluate(&position));
+            position.unmake_move();
+        }
+    }
+
+    #[test]
+    fn evaluate_material_from_side_to_move() {
+        init_tables();
+        let white = evaluate(&Position::from("4k3/8/8/8/8/8/8/R3K3 w - - 0 1"));
+        let black = evaluate(&Position::from("4k3/8/8/8/8/8/8/R3K3 b - - 0 1"));
+        assert!(white > 400);
+        assert!(white == -black);
+    }
+}
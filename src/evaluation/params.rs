@@ -0,0 +1,261 @@
+use std::fmt::Write;
+
+use super::{king_safety, mobility, pawns, psqt, S};
+
+/// All tunable evaluation weights. The defaults are the constants of the evaluation modules,
+/// which the tuner writes back under the same names.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EvalParams {
+    pub material: [S; 6],
+    pub psqt: [[S; 64]; 6],
+    pub doubled: S,
+    pub isolated: S,
+    pub backward: S,
+    pub connected: [S; 8],
+    pub passed: [S; 8],
+    pub passed_free_path: [S; 8],
+    pub passed_enemy_king_distance: S,
+    pub passed_own_king_distance: S,
+    pub king_attack_weights: [i32; 6],
+    pub max_king_danger: i32,
+    pub shelter: [S; 8],
+    pub storm: [S; 8],
+    pub semi_open_file_near_king: S,
+    pub open_file_near_king: S,
+    pub mobility: [S; 6],
+    pub bishop_pair: S,
+    pub rook_open_file: S,
+    pub rook_semi_open_file: S,
+    pub rook_on_seventh: S,
+    pub knight_outpost: S,
+    pub trapped_rook: S,
+    pub trapped_bishop: S,
+}
+
+/// Parameters the engine evaluates with
+pub static DEFAULT_PARAMS: EvalParams = EvalParams {
+    material: psqt::MATERIAL,
+    psqt: psqt::PSQT,
+    doubled: pawns::DOUBLED,
+    isolated: pawns::ISOLATED,
+    backward: pawns::BACKWARD,
+    connected: pawns::CONNECTED,
+    passed: pawns::PASSED,
+    passed_free_path: pawns::PASSED_FREE_PATH,
+    passed_enemy_king_distance: pawns::PASSED_ENEMY_KING_DISTANCE,
+    passed_own_king_distance: pawns::PASSED_OWN_KING_DISTANCE,
+    king_attack_weights: king_safety::KING_ATTACK_WEIGHTS,
+    max_king_danger: king_safety::MAX_KING_DANGER,
+    shelter: king_safety::SHELTER,
+    storm: king_safety::STORM,
+    semi_open_file_near_king: king_safety::SEMI_OPEN_FILE_NEAR_KING,
+    open_file_near_king: king_safety::OPEN_FILE_NEAR_KING,
+    mobility: mobility::MOBILITY,
+    bishop_pair: mobility::BISHOP_PAIR,
+    rook_open_file: mobility::ROOK_OPEN_FILE,
+    rook_semi_open_file: mobility::ROOK_SEMI_OPEN_FILE,
+    rook_on_seventh: mobility::ROOK_ON_SEVENTH,
+    knight_outpost: mobility::KNIGHT_OUTPOST,
+    trapped_rook: mobility::TRAPPED_ROOK,
+    trapped_bishop: mobility::TRAPPED_BISHOP,
+};
+
+impl Default for EvalParams {
+    fn default() -> Self {
+        DEFAULT_PARAMS.clone()
+    }
+}
+
+/// A group of parameters declared as one constant
+pub enum ParamGroup<'a> {
+    Score(&'a mut S),
+    Scores(&'a mut [S]),
+    Tables(&'a mut [[S; 64]]),
+    Int(&'a mut i32),
+    Ints(&'a mut [i32]),
+}
+
+impl ParamGroup<'_> {
+    /// Calls `func` on every single weight, middlegame before endgame
+    fn for_each(&mut self, mut func: impl FnMut(&mut i32)) {
+        let mut score = |s: &mut S| {
+            func(&mut s.0);
+            func(&mut s.1);
+        };
+        match self {
+            ParamGroup::Score(s) => score(s),
+            ParamGroup::Scores(scores) => scores.iter_mut().for_each(score),
+            ParamGroup::Tables(tables) => tables.iter_mut().flatten().for_each(score),
+            ParamGroup::Int(value) => func(value),
+            ParamGroup::Ints(values) => values.iter_mut().for_each(func),
+        }
+    }
+}
+
+impl EvalParams {
+    /// Returns the parameter groups by the name of their constant in a fixed order
+    pub fn groups(&mut self) -> Vec<(&'static str, ParamGroup<'_>)> {
+        vec![
+            ("MATERIAL", ParamGroup::Scores(&mut self.material)),
+            ("PSQT", ParamGroup::Tables(&mut self.psqt)),
+            ("DOUBLED", ParamGroup::Score(&mut self.doubled)),
+            ("ISOLATED", ParamGroup::Score(&mut self.isolated)),
+            ("BACKWARD", ParamGroup::Score(&mut self.backward)),
+            ("CONNECTED", ParamGroup::Scores(&mut self.connected)),
+            ("PASSED", ParamGroup::Scores(&mut self.passed)),
+            (
+                "PASSED_FREE_PATH",
+                ParamGroup::Scores(&mut self.passed_free_path),
+            ),
+            (
+                "PASSED_ENEMY_KING_DISTANCE",
+                ParamGroup::Score(&mut self.passed_enemy_king_distance),
+            ),
+            (
+                "PASSED_OWN_KING_DISTANCE",
+                ParamGroup::Score(&mut self.passed_own_king_distance),
+            ),
+            (
+                "KING_ATTACK_WEIGHTS",
+                ParamGroup::Ints(&mut self.king_attack_weights),
+            ),
+            (
+                "MAX_KING_DANGER",
+                ParamGroup::Int(&mut self.max_king_danger),
+            ),
+            ("SHELTER", ParamGroup::Scores(&mut self.shelter)),
+            ("STORM", ParamGroup::Scores(&mut self.storm)),
+            (
+                "SEMI_OPEN_FILE_NEAR_KING",
+                ParamGroup::Score(&mut self.semi_open_file_near_king),
+            ),
+            (
+                "OPEN_FILE_NEAR_KING",
+                ParamGroup::Score(&mut self.open_file_near_king),
+            ),
+            ("MOBILITY", ParamGroup::Scores(&mut self.mobility)),
+            ("BISHOP_PAIR", ParamGroup::Score(&mut self.bishop_pair)),
+            (
+                "ROOK_OPEN_FILE",
+                ParamGroup::Score(&mut self.rook_open_file),
+            ),
+            (
+                "ROOK_SEMI_OPEN_FILE",
+                ParamGroup::Score(&mut self.rook_semi_open_file),
+            ),
+            (
+                "ROOK_ON_SEVENTH",
+                ParamGroup::Score(&mut self.rook_on_seventh),
+            ),
+            (
+                "KNIGHT_OUTPOST",
+                ParamGroup::Score(&mut self.knight_outpost),
+            ),
+            ("TRAPPED_ROOK", ParamGroup::Score(&mut self.trapped_rook)),
+            (
+                "TRAPPED_BISHOP",
+                ParamGroup::Score(&mut self.trapped_bishop),
+            ),
+        ]
+    }
+
+    /// Flattens all weights into a single vector
+    pub fn to_vector(&self) -> Vec<i32> {
+        let mut params = self.clone();
+        let mut values = Vec::new();
+        for (_, mut group) in params.groups() {
+            group.for_each(|value| values.push(*value));
+        }
+        values
+    }
+
+    /// Builds the parameters from a vector in the order of `to_vector`
+    pub fn from_vector(values: &[i32]) -> Self {
+        let mut params = EvalParams::default();
+        let mut values = values.iter();
+        for (_, mut group) in params.groups() {
+            group.for_each(|value| *value = *values.next().expect("parameter vector too short"));
+        }
+        assert!(values.next().is_none(), "parameter vector too long");
+        params
+    }
+
+    /// Formats the parameters as Rust constants with the names used by the evaluation modules
+    pub fn to_rust_source(&self) -> String {
+        let format_scores = |scores: &[S], indent: &str| -> String {
+            let mut source = String::from("[\n");
+            for row in scores.chunks(8) {
+                source.push_str(indent);
+                source.push_str("    ");
+                let row: Vec<String> = row.iter().map(|s| format!("S({}, {})", s.0, s.1)).collect();
+                source.push_str(&row.join(", "));
+                source.push_str(",\n");
+            }
+            source.push_str(indent);
+            source.push(']');
+            source
+        };
+
+        let mut params = self.clone();
+        let mut source = String::new();
+        for (name, group) in params.groups() {
+            let _ = match group {
+                ParamGroup::Score(s) => {
+                    writeln!(source, "pub const {}: S = S({}, {});", name, s.0, s.1)
+                }
+                ParamGroup::Scores(scores) => writeln!(
+                    source,
+                    "pub const {}: [S; {}] = {};",
+                    name,
+                    scores.len(),
+                    format_scores(scores, "")
+                ),
+                ParamGroup::Tables(tables) => {
+                    let tables: Vec<String> = tables
+                        .iter()
+                        .map(|table| format!("    {}", format_scores(table, "    ")))
+                        .collect();
+                    writeln!(
+                        source,
+                        "#[rustfmt::skip]\npub const {}: [[S; 64]; {}] = [\n{},\n];",
+                        name,
+                        tables.len(),
+                        tables.join(",\n")
+                    )
+                }
+                ParamGroup::Int(value) => writeln!(source, "pub const {}: i32 = {};", name, value),
+                ParamGroup::Ints(values) => writeln!(
+                    source,
+                    "pub const {}: [i32; {}] = {:?};",
+                    name,
+                    values.len(),
+                    values
+                ),
+            };
+        }
+        source
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn convert_parameters_to_vector_and_back() {
+        let values = DEFAULT_PARAMS.to_vector();
+        // Material and piece-square tables alone have (6 + 6 * 64) scores of two weights each
+        assert!(values.len() > 2 * (6 + 6 * 64));
+        assert!(values[..2] == [psqt::MATERIAL[0].0, psqt::MATERIAL[0].1]);
+        assert!(EvalParams::from_vector(&values) == DEFAULT_PARAMS);
+
+        let mut shifted = values.clone();
+        shifted[0] += 10;
+        assert!(EvalParams::from_vector(&shifted).material[0] == psqt::MATERIAL[0] + S(10, 0));
+
+        let source = DEFAULT_PARAMS.to_rust_source();
+        assert!(source.contains("pub const DOUBLED: S = S(-10, -25);"));
+        assert!(source.contains("pub const KING_ATTACK_WEIGHTS: [i32; 6] = [0, 2, 2, 3, 5, 0];"));
+        assert!(source.contains("pub const PSQT: [[S; 64]; 6] = ["));
+    }
+}
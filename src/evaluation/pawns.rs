@@ -0,0 +1,239 @@
+use super::{params::EvalParams, S};
+use crate::{
+    piece::PieceTypes,
+    position::Position,
+    util::{
+        bb_from_square, enumerate_bits, file_fill, front_span, opp, pawn_attacks_bb,
+        relative_rank_of, shift_east, shift_forward, shift_west, square_distance, Bitboard, Color,
+    },
+};
+
+/// Penalty for each pawn with another pawn of its color in front of it
+pub const DOUBLED: S = S(-10, -25);
+/// Penalty for pawns without pawns of their color on the adjacent files
+pub const ISOLATED: S = S(-10, -15);
+/// Penalty for pawns that cannot advance safely and cannot be defended by other pawns
+pub const BACKWARD: S = S(-8, -12);
+/// Bonus for pawns defended by or side by side with a pawn of their color, by relative rank
+pub const CONNECTED: [S; 8] = [
+    S(0, 0),
+    S(3, 0),
+    S(5, 2),
+    S(8, 5),
+    S(15, 12),
+    S(25, 25),
+    S(40, 40),
+    S(0, 0),
+];
+/// Bonus for passed pawns by relative rank
+pub const PASSED: [S; 8] = [
+    S(0, 0),
+    S(0, 10),
+    S(5, 15),
+    S(10, 25),
+    S(25, 45),
+    S(45, 80),
+    S(70, 130),
+    S(0, 0),
+];
+/// Bonus for passed pawns without any piece on the way to promotion, by relative rank
+pub const PASSED_FREE_PATH: [S; 8] = [
+    S(0, 0),
+    S(0, 0),
+    S(0, 2),
+    S(0, 5),
+    S(5, 15),
+    S(10, 30),
+    S(15, 50),
+    S(0, 0),
+];
+/// Bonus per square the enemy king is away from the stop square of an advanced passed pawn
+pub const PASSED_ENEMY_KING_DISTANCE: S = S(0, 4);
+/// Penalty per square the own king is away from the stop square of an advanced passed pawn
+pub const PASSED_OWN_KING_DISTANCE: S = S(0, -2);
+
+/// Number of entries of the pawn hash table, a power of two
+const PAWN_TABLE_SIZE: usize = 1 << 14;
+
+/// Pawn structure evaluation of both sides, which only depends on the pawns
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PawnEntry {
+    key: u64,
+    /// Score from white's point of view
+    pub score: S,
+    /// Passed pawns of both colors
+    pub passed: Bitboard,
+}
+
+/// Cache of pawn structure evaluations indexed by the pawn hash
+pub struct PawnTable {
+    entries: Vec<PawnEntry>,
+}
+
+impl Default for PawnTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PawnTable {
+    pub fn new() -> Self {
+        PawnTable {
+            entries: vec![PawnEntry::default(); PAWN_TABLE_SIZE],
+        }
+    }
+
+    /// Returns the pawn structure evaluation of a position, computing it on a cache miss
+    pub fn probe(&mut self, position: &Position, params: &EvalParams) -> PawnEntry {
+        let key = position.pawn_hash();
+        let entry = &mut self.entries[key as usize & (PAWN_TABLE_SIZE - 1)];
+        // The default entry is the correct evaluation of positions without pawns
+        if entry.key != key {
+            *entry = evaluate_pawn_structure(position, params);
+        }
+        *entry
+    }
+}
+
+/// Evaluates doubled, isolated, backward, connected and passed pawns of both sides
+pub fn evaluate_pawn_structure(position: &Position, params: &EvalParams) -> PawnEntry {
+    let (white_score, white_passed) = pawn_terms(position, Color::White, params);
+    let (black_score, black_passed) = pawn_terms(position, Color::Black, params);
+
+    PawnEntry {
+        key: position.pawn_hash(),
+        score: white_score - black_score,
+        passed: white_passed | black_passed,
+    }
+}
+
+pub(super) fn pawn_terms(position: &Position, color: Color, params: &EvalParams) -> (S, Bitboard) {
+    let own = position.piece_bb(color, PieceTypes::PAWN);
+    let enemy = position.piece_bb(opp(color), PieceTypes::PAWN);
+    let own_attacks = pawn_attacks_bb(own, color);
+    let enemy_attacks = pawn_attacks_bb(enemy, opp(color));
+
+    let own_files = file_fill(own);
+    let enemy_front = front_span(enemy, opp(color));
+    // Squares on which a pawn is blocked or can be captured by enemy pawns on its way
+    let stoppers = enemy_front | shift_east(enemy_front) | shift_west(enemy_front);
+    // Squares own pawns attack now or after advancing
+    let attack_span = own_attacks | front_span(own_attacks, color);
+
+    let rear = own & front_span(own, opp(color));
+    let passed = own & !stoppers & !rear;
+    let isolated = own & !(shift_east(own_files) | shift_west(own_files));
+    let backward = own & !isolated & shift_forward(enemy_attacks & !attack_span, opp(color));
+    let connected = own & (own_attacks | shift_east(own) | shift_west(own));
+
+    let mut score = params.doubled * rear.count_ones() as i32
+        + params.isolated * isolated.count_ones() as i32
+        + params.backward * backward.count_ones() as i32;
+    enumerate_bits(connected, |square| {
+        score += params.connected[relative_rank_of(square, color) as usize];
+    });
+    enumerate_bits(passed, |square| {
+        score += params.passed[relative_rank_of(square, color) as usize];
+    });
+    (score, passed)
+}
+
+/// Scores the passed pawn terms depending on other pieces: a free path to promotion and the
+/// distance of both kings to the stop square
+pub fn evaluate_passers(position: &Position, passed: Bitboard, params: &EvalParams) -> S {
+    passer_terms(position, passed, Color::White, params)
+        - passer_terms(position, passed, Color::Black, params)
+}
+
+pub(super) fn passer_terms(
+    position: &Position,
+    passed: Bitboard,
+    color: Color,
+    params: &EvalParams,
+) -> S {
+    let occupancy = position.all_pieces_bb(None);
+    let own_king = position.king_square(color);
+    let enemy_king = position.king_square(opp(color));
+    let mut score = S::default();
+
+    enumerate_bits(
+        passed & position.piece_bb(color, PieceTypes::PAWN),
+        |square| {
+            let rank = relative_rank_of(square, color) as usize;
+            let bb = bb_from_square(square);
+            if front_span(bb, color) & occupancy == 0 {
+                score += params.passed_free_path[rank];
+            }
+
+            let weight = rank as i32 - 2;
+            if weight > 0 {
+                let stop_sq = shift_forward(bb, color).trailing_zeros() as u8;
+                score += params.passed_enemy_king_distance
+                    * (square_distance(enemy_king, stop_sq) as i32 * weight);
+                score += params.passed_own_king_distance
+                    * (square_distance(own_king, stop_sq) as i32 * weight);
+            }
+        },
+    );
+    score
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{attacks::init_tables, evaluation::params::DEFAULT_PARAMS};
+
+    use super::*;
+
+    fn terms(fen: &str, color: Color) -> (S, Bitboard) {
+        pawn_terms(&Position::from(fen), color, &DEFAULT_PARAMS)
+    }
+
+    #[test]
+    fn detect_pawn_structure_correctly() {
+        init_tables();
+        // Doubled and isolated pawns on the c-file, e4 is supported by f3. Without enemy pawns
+        // all pawns but the rear c-pawn are passed.
+        let (score, passed) = terms("4k3/8/8/8/2P1P3/2P2P2/8/4K3 w - - 0 1", Color::White);
+        assert!(passed == bb_from_square(26) | bb_from_square(28) | bb_from_square(21));
+        assert!(score == DOUBLED + ISOLATED * 2 + CONNECTED[3] + PASSED[3] * 2 + PASSED[2]);
+
+        // The d-pawn cannot advance to d4 and cannot be supported by the e-pawn anymore
+        let (score, passed) = terms("4k3/8/8/2p5/4P3/3P4/8/4K3 w - - 0 1", Color::White);
+        assert!(passed == bb_from_square(28));
+        assert!(score == BACKWARD + CONNECTED[3] + PASSED[3]);
+
+        // The a-pawn is passed, the b-pawn is opposed by the c-pawn and the rear pawn is not
+        let (_, passed) = terms("4k3/8/2p5/8/PP6/P7/8/4K3 w - - 0 1", Color::White);
+        assert!(passed == bb_from_square(24));
+        let (_, passed) = terms("4k3/8/2p5/8/PP6/P7/8/4K3 w - - 0 1", Color::Black);
+        assert!(passed == 0);
+    }
+
+    #[test]
+    fn cache_pawn_structure_by_pawn_hash() {
+        init_tables();
+        let mut table = PawnTable::new();
+        let position = Position::from("4k3/8/2p5/8/PP6/P7/8/4K3 w - - 0 1");
+        let entry = table.probe(&position, &DEFAULT_PARAMS);
+        assert!(entry == evaluate_pawn_structure(&position, &DEFAULT_PARAMS));
+
+        // Kings do not change the pawn structure
+        let moved_king = Position::from("8/4k3/2p5/8/PP6/P7/8/3K4 b - - 0 1");
+        assert!(moved_king.pawn_hash() == position.pawn_hash());
+        assert!(table.probe(&moved_king, &DEFAULT_PARAMS) == entry);
+    }
+
+    #[test]
+    fn reward_advanced_passers_near_own_king() {
+        init_tables();
+        let position = Position::from("8/8/1P6/8/8/8/1K4k1/8 w - - 0 1");
+        let near = evaluate_passers(&position, position.pieces[0], &DEFAULT_PARAMS);
+        let position = Position::from("8/k7/1P6/8/8/8/6K1/8 w - - 0 1");
+        let far = evaluate_passers(&position, position.pieces[0], &DEFAULT_PARAMS);
+        assert!(near.1 > far.1);
+        // A blocked passer loses its free path bonus
+        let position = Position::from("8/1k6/1P6/8/8/8/6K1/8 w - - 0 1");
+        let blocked = evaluate_passers(&position, position.pieces[0], &DEFAULT_PARAMS);
+        assert!(far.1 - blocked.1 > 0);
+    }
+}
@@ -0,0 +1,82 @@
+use super::S;
+
+/// Material values indexed by piece type
+pub const MATERIAL: [S; 6] = [
+    S(82, 94),
+    S(337, 281),
+    S(365, 297),
+    S(477, 512),
+    S(1025, 936),
+    S(0, 0),
+];
+
+/// Piece-square tables indexed by piece type, laid out as seen from white with a8 first
+#[rustfmt::skip]
+pub const PSQT: [[S; 64]; 6] = [
+    // Pawn
+    [
+        S(  0,   0), S(  0,   0), S(  0,   0), S(  0,   0), S(  0,   0), S(  0,   0), S(  0,   0), S(  0,   0),
+        S( 50,  80), S( 50,  80), S( 50,  80), S( 50,  80), S( 50,  80), S( 50,  80), S( 50,  80), S( 50,  80),
+        S( 10,  50), S( 10,  50), S( 20,  50), S( 30,  50), S( 30,  50), S( 20,  50), S( 10,  50), S( 10,  50),
+        S(  5,  30), S(  5,  30), S( 10,  30), S( 25,  30), S( 25,  30), S( 10,  30), S(  5,  30), S(  5,  30),
+        S(  0,  15), S(  0,  15), S(  0,  15), S( 20,  15), S( 20,  15), S(  0,  15), S(  0,  15), S(  0,  15),
+        S(  5,   5), S( -5,   5), S(-10,   5), S(  0,   5), S(  0,   5), S(-10,   5), S( -5,   5), S(  5,   5),
+        S(  5,   0), S( 10,   0), S( 10,   0), S(-20,   0), S(-20,   0), S( 10,   0), S( 10,   0), S(  5,   0),
+        S(  0,   0), S(  0,   0), S(  0,   0), S(  0,   0), S(  0,   0), S(  0,   0), S(  0,   0), S(  0,   0),
+    ],
+    // Knight
+    [
+        S(-50, -50), S(-40, -40), S(-30, -30), S(-30, -30), S(-30, -30), S(-30, -30), S(-40, -40), S(-50, -50),
+        S(-40, -40), S(-20, -20), S(  0,   0), S(  0,   0), S(  0,   0), S(  0,   0), S(-20, -20), S(-40, -40),
+        S(-30, -30), S(  0,   0), S( 10,  10), S( 15,  15), S( 15,  15), S( 10,  10), S(  0,   0), S(-30, -30),
+        S(-30, -30), S(  5,   5), S( 15,  15), S( 20,  20), S( 20,  20), S( 15,  15), S(  5,   5), S(-30, -30),
+        S(-30, -30), S(  0,   0), S( 15,  15), S( 20,  20), S( 20,  20), S( 15,  15), S(  0,   0), S(-30, -30),
+        S(-30, -30), S(  5,   5), S( 10,  10), S( 15,  15), S( 15,  15), S( 10,  10), S(  5,   5), S(-30, -30),
+        S(-40, -40), S(-20, -20), S(  0,   0), S(  5,   5), S(  5,   5), S(  0,   0), S(-20, -20), S(-40, -40),
+        S(-50, -50), S(-40, -40), S(-30, -30), S(-30, -30), S(-30, -30), S(-30, -30), S(-40, -40), S(-50, -50),
+    ],
+    // Bishop
+    [
+        S(-20, -15), S(-10, -10), S(-10, -10), S(-10, -10), S(-10, -10), S(-10, -10), S(-10, -10), S(-20, -15),
+        S(-10, -10), S(  0,   0), S(  0,   0), S(  0,   0), S(  0,   0), S(  0,   0), S(  0,   0), S(-10, -10),
+        S(-10, -10), S(  0,   0), S(  5,   5), S( 10,   5), S( 10,   5), S(  5,   5), S(  0,   0), S(-10, -10),
+        S(-10, -10), S(  5,   0), S(  5,   5), S( 10,  10), S( 10,  10), S(  5,   5), S(  5,   0), S(-10, -10),
+        S(-10, -10), S(  0,   0), S( 10,   5), S( 10,  10), S( 10,  10), S( 10,   5), S(  0,   0), S(-10, -10),
+        S(-10, -10), S( 10,   0), S( 10,   5), S( 10,   5), S( 10,   5), S( 10,   5), S( 10,   0), S(-10, -10),
+        S(-10, -10), S(  5,   0), S(  0,   0), S(  0,   0), S(  0,   0), S(  0,   0), S(  5,   0), S(-10, -10),
+        S(-20, -15), S(-10, -10), S(-10, -10), S(-10, -10), S(-10, -10), S(-10, -10), S(-10, -10), S(-20, -15),
+    ],
+    // Rook
+    [
+        S(  0,   5), S(  0,   5), S(  0,   5), S(  0,   5), S(  0,   5), S(  0,   5), S(  0,   5), S(  0,   5),
+        S(  5,  10), S( 10,  10), S( 10,  10), S( 10,  10), S( 10,  10), S( 10,  10), S( 10,  10), S(  5,  10),
+        S( -5,   0), S(  0,   0), S(  0,   0), S(  0,   0), S(  0,   0), S(  0,   0), S(  0,   0), S( -5,   0),
+        S( -5,   0), S(  0,   0), S(  0,   0), S(  0,   0), S(  0,   0), S(  0,   0), S(  0,   0), S( -5,   0),
+        S( -5,   0), S(  0,   0), S(  0,   0), S(  0,   0), S(  0,   0), S(  0,   0), S(  0,   0), S( -5,   0),
+        S( -5,   0), S(  0,   0), S(  0,   0), S(  0,   0), S(  0,   0), S(  0,   0), S(  0,   0), S( -5,   0),
+        S( -5,   0), S(  0,   0), S(  0,   0), S(  0,   0), S(  0,   0), S(  0,   0), S(  0,   0), S( -5,   0),
+        S(  0,   0), S(  0,   0), S(  0,   0), S(  5,   0), S(  5,   0), S(  0,   0), S(  0,   0), S(  0,   0),
+    ],
+    // Queen
+    [
+        S(-20, -20), S(-10, -10), S(-10, -10), S( -5,  -5), S( -5,  -5), S(-10, -10), S(-10, -10), S(-20, -20),
+        S(-10, -10), S(  0,   0), S(  0,   5), S(  0,   5), S(  0,   5), S(  0,   5), S(  0,   0), S(-10, -10),
+        S(-10, -10), S(  0,   5), S(  5,  10), S(  5,  15), S(  5,  15), S(  5,  10), S(  0,   5), S(-10, -10),
+        S( -5,  -5), S(  0,   5), S(  5,  15), S(  5,  20), S(  5,  20), S(  5,  15), S(  0,   5), S( -5,  -5),
+        S(  0,  -5), S(  0,   5), S(  5,  15), S(  5,  20), S(  5,  20), S(  5,  15), S(  0,   5), S( -5,  -5),
+        S(-10, -10), S(  5,   5), S(  5,  10), S(  5,  15), S(  5,  15), S(  5,  10), S(  0,   5), S(-10, -10),
+        S(-10, -10), S(  0,   0), S(  5,   5), S(  0,   5), S(  0,   5), S(  0,   5), S(  0,   0), S(-10, -10),
+        S(-20, -20), S(-10, -10), S(-10, -10), S( -5,  -5), S( -5,  -5), S(-10, -10), S(-10, -10), S(-20, -20),
+    ],
+    // King
+    [
+        S(-30, -50), S(-40, -40), S(-40, -30), S(-50, -20), S(-50, -20), S(-40, -30), S(-40, -40), S(-30, -50),
+        S(-30, -30), S(-40, -20), S(-40, -10), S(-50,   0), S(-50,   0), S(-40, -10), S(-40, -20), S(-30, -30),
+        S(-30, -30), S(-40, -10), S(-40,  20), S(-50,  30), S(-50,  30), S(-40,  20), S(-40, -10), S(-30, -30),
+        S(-30, -30), S(-40, -10), S(-40,  30), S(-50,  40), S(-50,  40), S(-40,  30), S(-40, -10), S(-30, -30),
+        S(-20, -30), S(-30, -10), S(-30,  30), S(-40,  40), S(-40,  40), S(-30,  30), S(-30, -10), S(-20, -30),
+        S(-10, -30), S(-20, -10), S(-20,  20), S(-20,  30), S(-20,  30), S(-20,  20), S(-20, -10), S(-10, -30),
+        S( 20, -30), S( 20, -30), S(  0,   0), S(  0,   0), S(  0,   0), S(  0,   0), S( 20, -30), S( 20, -30),
+        S( 20, -50), S( 30, -30), S( 10, -30), S(  0, -30), S(  0, -30), S( 10, -30), S( 30, -30), S( 20, -50),
+    ],
+];
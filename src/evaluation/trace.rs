@@ -0,0 +1,185 @@
+use std::fmt;
+
+use super::{
+    game_phase, king_safety::king_safety, material, mobility, params::EvalParams, pawns,
+    piece_squares, MAX_PHASE, S,
+};
+use crate::{position::Position, util::Color};
+
+/// Groups of evaluation terms listed in the trace
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Term {
+    Material,
+    Psqt,
+    Pawns,
+    Passers,
+    KingSafety,
+    Mobility,
+    Pieces,
+}
+
+impl Term {
+    pub const ALL: [Term; 7] = [
+        Term::Material,
+        Term::Psqt,
+        Term::Pawns,
+        Term::Passers,
+        Term::KingSafety,
+        Term::Mobility,
+        Term::Pieces,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Term::Material => "Material",
+            Term::Psqt => "PST",
+            Term::Pawns => "Pawns",
+            Term::Passers => "Passed pawns",
+            Term::KingSafety => "King safety",
+            Term::Mobility => "Mobility",
+            Term::Pieces => "Pieces",
+        }
+    }
+}
+
+/// Contribution of every evaluation term per side and game phase
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EvalTrace {
+    terms: [[S; 2]; Term::ALL.len()],
+    pub phase: i32,
+    pub side_to_move: Color,
+}
+
+impl EvalTrace {
+    /// Evaluates the position term by term
+    pub fn new(position: &Position, params: &EvalParams) -> Self {
+        let passed = pawns::evaluate_pawn_structure(position, params).passed;
+        let mut terms = [[S::default(); 2]; Term::ALL.len()];
+
+        for color in [Color::White, Color::Black] {
+            for (idx, term) in Term::ALL.iter().enumerate() {
+                terms[idx][color as usize] = match term {
+                    Term::Material => material(position, color, params),
+                    Term::Psqt => piece_squares(position, color, params),
+                    Term::Pawns => pawns::pawn_terms(position, color, params).0,
+                    Term::Passers => pawns::passer_terms(position, passed, color, params),
+                    Term::KingSafety => king_safety(position, color, params),
+                    Term::Mobility => mobility::mobility(position, color, params),
+                    Term::Pieces => mobility::piece_activity(position, color, params),
+                };
+            }
+        }
+
+        EvalTrace {
+            terms,
+            phase: game_phase(position),
+            side_to_move: position.side_to_move(),
+        }
+    }
+
+    /// Returns the score of a term for one side
+    pub fn get(&self, term: Term, color: Color) -> S {
+        self.terms[term as usize][color as usize]
+    }
+
+    /// Returns the score of a term from white's point of view
+    pub fn term(&self, term: Term) -> S {
+        self.get(term, Color::White) - self.get(term, Color::Black)
+    }
+
+    /// Returns the sum of all terms from white's point of view
+    pub fn total(&self) -> S {
+        Term::ALL
+            .iter()
+            .fold(S::default(), |total, &term| total + self.term(term))
+    }
+
+    /// Returns the tapered evaluation from white's point of view
+    pub fn score(&self) -> i32 {
+        self.total().taper(self.phase)
+    }
+}
+
+impl fmt::Display for EvalTrace {
+    /// Formats the trace as a table of middlegame and endgame scores in centipawns
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let separator = " -------------+-------------+-------------+-------------";
+        writeln!(f, "         Term |    White    |    Black    |    Total")?;
+        writeln!(f, "              |   MG    EG  |   MG    EG  |   MG    EG")?;
+        writeln!(f, "{}", separator)?;
+
+        let row = |f: &mut fmt::Formatter, name: &str, white: S, black: S, total: S| {
+            writeln!(
+                f,
+                " {:>12} | {:>5} {:>5} | {:>5} {:>5} | {:>5} {:>5}",
+                name, white.0, white.1, black.0, black.1, total.0, total.1
+            )
+        };
+        for term in Term::ALL {
+            row(
+                f,
+                term.name(),
+                self.get(term, Color::White),
+                self.get(term, Color::Black),
+                self.term(term),
+            )?;
+        }
+        writeln!(f, "{}", separator)?;
+        let white = Term::ALL.iter().fold(S::default(), |sum, &term| {
+            sum + self.get(term, Color::White)
+        });
+        let black = Term::ALL.iter().fold(S::default(), |sum, &term| {
+            sum + self.get(term, Color::Black)
+        });
+        row(f, "Total", white, black, self.total())?;
+
+        writeln!(f)?;
+        writeln!(f, "Phase: {}/{}", self.phase, MAX_PHASE)?;
+        let score = self.score();
+        let side_score = match self.side_to_move {
+            Color::White => score,
+            Color::Black => -score,
+        };
+        write!(
+            f,
+            "Evaluation: {} cp (white side), {} cp (side to move)",
+            score, side_score
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        attacks::init_tables,
+        evaluation::{evaluate, params::DEFAULT_PARAMS, psqt::MATERIAL},
+    };
+
+    use super::*;
+
+    #[test]
+    fn sum_up_to_the_evaluation() {
+        init_tables();
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 b - - 0 1",
+        ] {
+            let position = Position::from(fen);
+            let trace = EvalTrace::new(&position, &DEFAULT_PARAMS);
+            let side_score = match position.side_to_move() {
+                Color::White => trace.score(),
+                Color::Black => -trace.score(),
+            };
+            assert!(side_score == evaluate(&position));
+        }
+
+        let trace = EvalTrace::new(
+            &Position::from("4k3/8/8/8/8/8/8/R3K3 w - - 0 1"),
+            &DEFAULT_PARAMS,
+        );
+        assert!(trace.term(Term::Material) == MATERIAL[3]);
+        assert!(trace.get(Term::Material, Color::Black) == S::default());
+        assert!(trace.to_string().lines().count() == 15);
+    }
+}
@@ -1,10 +1,11 @@
-use crate::{castling_rights::CastlingRights, util::Color, piece::Piece};
+use crate::{castling_rights::CastlingRights, piece::Piece, util::Color};
 
 pub type Squares = [Option<Piece>; 64];
 pub type EnPassantSquare = Option<u8>;
 pub type HalfMoveClock = usize;
 pub type FullMoveNumber = usize;
 
+#[derive(Clone)]
 pub struct BoardState(
     pub Squares,
     pub Color,
@@ -30,38 +31,57 @@ pub fn san_to_int(san: &str) -> Option<u8> {
         return None;
     }
 
-    let file = san.chars().nth(0)?;
+    let file = san.chars().next()?;
     let rank = san.chars().nth(1)?;
 
-    let file_value = (file as u8 - b'a') as u8;
+    let file_value = (file as u8).wrapping_sub(b'a');
     let rank_value = rank.to_digit(10)? as u8;
 
-    if file_value >= 8 || rank_value < 1 || rank_value > 8 {
+    if file_value >= 8 || !(1..=8).contains(&rank_value) {
         return None;
     }
 
     Some((rank_value - 1) * 8 + file_value)
 }
 
-/// Translates an Edward-Forsyth-Notation to a full board state
+/// Translates the index of a square (0-63) to its Standard Algebraic Notation
+pub fn int_to_san(square: u8) -> String {
+    let file = (b'a' + (square & 7)) as char;
+    let rank = (b'1' + (square >> 3)) as char;
+    format!("{}{}", file, rank)
+}
+
+/// Translates an Edward-Forsyth-Notation to a full board state. Missing move counters default to
+/// `0 1` as in EPD.
 pub fn parse_fen(fen: &str) -> Result<BoardState, &str> {
-    let mut parts = fen.splitn(6, " ");
+    let fields: Vec<&str> = fen.split_whitespace().collect();
+    let mut parts = match fields.len() {
+        4 => [fields.as_slice(), &["0", "1"]].concat(),
+        5 => [fields.as_slice(), &["1"]].concat(),
+        6 => fields,
+        0..=3 => return Err("Missing fields in FEN"),
+        _ => return Err("Too many fields in FEN"),
+    }
+    .into_iter();
 
     let mut mailbox: [Option<Piece>; 64] = [None; 64];
     let mut castling_rights = CastlingRights::empty();
     let piece_placements = parts.next().unwrap();
-    let ranks = piece_placements.split("/");
+    let ranks: Vec<&str> = piece_placements.split('/').collect();
+    if ranks.len() != 8 {
+        return Err("Invalid number of ranks in FEN");
+    }
 
-    for (rank_idx, rank) in ranks.enumerate() {
+    for (rank_idx, rank) in ranks.into_iter().enumerate() {
         let pieces = rank.chars();
         let mut file_idx = 0;
-        for (_, piece) in pieces.enumerate() {
+        for piece in pieces {
             if file_idx > 7 {
                 return Err("Invalid piece placement in FEN");
             }
             match piece.to_digit(10) {
                 Some(number) => {
-                    if number < 1 || number > 8 {
+                    if !(1..=8).contains(&number) {
                         return Err("Invalid piece placement in FEN");
                     }
                     file_idx += number as usize;
@@ -75,11 +95,34 @@ pub fn parse_fen(fen: &str) -> Result<BoardState, &str> {
                 }
             }
         }
+        if file_idx != 8 {
+            return Err("Invalid piece placement in FEN");
+        }
     }
 
-    let active_color = match parts.next().unwrap().chars().nth(0).unwrap() {
-        'b' => Color::Black,
-        'w' => Color::White,
+    // Positions without exactly one king per side cannot be searched
+    for king in ['K', 'k'] {
+        let kings = mailbox
+            .iter()
+            .filter(|piece| piece.map(|piece| piece.to_char()) == Some(king))
+            .count();
+        if kings != 1 {
+            return Err("Invalid number of kings in FEN");
+        }
+    }
+
+    // Pawns never stand on the first or last rank, they promote on reaching it
+    let back_ranks = mailbox[..8].iter().chain(&mailbox[56..]);
+    if back_ranks
+        .flatten()
+        .any(|piece| piece.to_char().eq_ignore_ascii_case(&'p'))
+    {
+        return Err("Invalid pawn placement in FEN");
+    }
+
+    let active_color = match parts.next().unwrap() {
+        "b" => Color::Black,
+        "w" => Color::White,
         _ => return Err("Invalid active color in FEN"),
     };
 
@@ -91,13 +134,14 @@ pub fn parse_fen(fen: &str) -> Result<BoardState, &str> {
             'K' => castling_rights.insert(CastlingRights::WHITE_KING_SIDE),
             'q' => castling_rights.insert(CastlingRights::BLACK_QUEEN_SIDE),
             'k' => castling_rights.insert(CastlingRights::BLACK_KING_SIDE),
+            '-' => (),
             _ => return Err("Invalid castling rights in FEN"),
         }
     }
 
     let en_passant_square = match parts.next().unwrap() {
         "-" => None,
-        san => san_to_int(san),
+        san => Some(san_to_int(san).ok_or("Invalid en passant square in FEN")?),
     };
 
     let halfmove_clock = match parts.next().unwrap().parse::<usize>() {
@@ -124,6 +168,71 @@ pub fn parse_fen(fen: &str) -> Result<BoardState, &str> {
     ))
 }
 
+/// Translates a full board state to its Edward-Forsyth-Notation
+pub fn format_fen(board_state: &BoardState) -> String {
+    let BoardState(
+        mailbox,
+        active_color,
+        castling_rights,
+        en_passant_square,
+        halfmove_clock,
+        fullmove_number,
+    ) = board_state;
+
+    let mut fen = String::new();
+
+    for rank_idx in (0..8).rev() {
+        let mut empty = 0;
+        for file_idx in 0..8 {
+            match mailbox[rank_idx * 8 + file_idx] {
+                Some(piece) => {
+                    if empty > 0 {
+                        fen.push_str(&empty.to_string());
+                        empty = 0;
+                    }
+                    fen.push(piece.to_char());
+                }
+                None => empty += 1,
+            }
+        }
+        if empty > 0 {
+            fen.push_str(&empty.to_string());
+        }
+        if rank_idx > 0 {
+            fen.push('/');
+        }
+    }
+
+    fen.push(' ');
+    fen.push(match active_color {
+        Color::White => 'w',
+        Color::Black => 'b',
+    });
+
+    fen.push(' ');
+    if castling_rights.is_empty() {
+        fen.push('-');
+    }
+    for (right, ch) in [
+        (CastlingRights::WHITE_KING_SIDE, 'K'),
+        (CastlingRights::WHITE_QUEEN_SIDE, 'Q'),
+        (CastlingRights::BLACK_KING_SIDE, 'k'),
+        (CastlingRights::BLACK_QUEEN_SIDE, 'q'),
+    ] {
+        if castling_rights.contains(right) {
+            fen.push(ch);
+        }
+    }
+
+    fen.push(' ');
+    match en_passant_square {
+        Some(square) => fen.push_str(&int_to_san(*square)),
+        None => fen.push('-'),
+    }
+
+    format!("{} {} {}", fen, halfmove_clock, fullmove_number)
+}
+
 #[cfg(test)]
 mod tests {
     use crate::piece::PieceTypes;
@@ -149,20 +258,74 @@ mod tests {
 
         assert!(mailbox[0].unwrap() == Piece::new(Color::White, PieceTypes::ROOK));
         assert!(mailbox[1].unwrap() == Piece::new(Color::White, PieceTypes::KNIGHT));
-        assert!(mailbox[6] == None);
+        assert!(mailbox[6].is_none());
         assert!(mailbox[63].unwrap() == Piece::new(Color::Black, PieceTypes::ROOK));
 
         assert!(active_color == Color::Black);
 
         assert!(castling_rights.bits() == 0b1111);
 
-        assert!(en_passant_square == None);
+        assert!(en_passant_square.is_none());
 
         assert!(halfmove_clock == 1);
 
         assert!(fullmove_number == 2);
     }
 
+    #[test]
+    fn parse_fen_without_castling_rights_correctly() {
+        let BoardState(_, _, castling_rights, en_passant_square, _, _) =
+            parse_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1").unwrap();
+
+        assert!(castling_rights.is_empty());
+        assert!(en_passant_square == Some(43));
+    }
+
+    #[test]
+    fn reject_invalid_fens() {
+        for fen in [
+            "",
+            "4k3/8/8/8/8/8/8/4K3 w",
+            "4k3/8/8/8/8/8/8/4K3 w - - 0 1 extra",
+            "4k3/8/8/8/8/8/8/8/4K3 w - - 0 1",
+            "4k3/8/8/8/8/8/4K3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/4K3R w - - 0 1",
+            "4k3/8/8/8/8/8/8/4K2 w - - 0 1",
+            "4k3/8/8/8/8/8/8/44K3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/8 w - - 0 1",
+            "4k3/8/8/8/8/8/8/3KK3 w - - 0 1",
+            "4k2P/8/8/8/8/8/8/4K3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/p3K3 b - - 0 1",
+            "4k3/8/8/8/8/8/8/4K3 white - - 0 1",
+            "4k3/8/8/8/8/8/8/4K3 w - z9 0 1",
+            "4k3/8/8/8/8/8/8/4K3 w - - x 1",
+        ] {
+            assert!(parse_fen(fen).is_err(), "{}", fen);
+        }
+    }
+
+    #[test]
+    fn default_missing_move_counters() {
+        let BoardState(_, _, _, _, halfmove_clock, fullmove_number) =
+            parse_fen("4k3/8/8/8/8/8/8/4K3 b - -").unwrap();
+        assert!(halfmove_clock == 0 && fullmove_number == 1);
+
+        let BoardState(_, _, _, _, halfmove_clock, fullmove_number) =
+            parse_fen("4k3/8/8/8/8/8/8/4K3 b - - 7").unwrap();
+        assert!(halfmove_clock == 7 && fullmove_number == 1);
+    }
+
+    #[test]
+    fn format_fen_correctly() {
+        for fen in [
+            "rnbqkbnr/pp1ppppp/8/2p5/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1",
+        ] {
+            assert!(format_fen(&parse_fen(fen).unwrap()) == fen);
+        }
+    }
+
     #[test]
     fn parse_san_to_index_correctly() {
         match san_to_int("a1") {
@@ -174,5 +337,8 @@ mod tests {
             Some(number) => assert!(number == 63),
             None => panic!("SAN d6 parsing failed"),
         }
+
+        assert!(int_to_san(0) == "a1");
+        assert!(int_to_san(43) == "d6");
     }
 }
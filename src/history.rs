@@ -0,0 +1,313 @@
+use crate::{
+    cmove::Move,
+    move_picker::MoveScorer,
+    piece::Piece,
+    position::Position,
+    search::{captured_value, MAX_PLY},
+    see::SEE_VALUES,
+};
+
+/// History scores saturate at this bound, see `update`
+pub const MAX_HISTORY: i32 = 16_384;
+
+/// Ordering bonus of the quiet move that refuted the previous move before
+const COUNTER_MOVE_BONUS: i32 = 1 << 16;
+
+/// Offset that moves captures losing material behind the quiet moves
+const BAD_CAPTURE_PENALTY: i32 = 1 << 16;
+
+/// Piece and target square of a move made earlier on the path to a node
+pub type ContinuationKey = Option<(Piece, u8)>;
+
+/// Returns the history bonus of a move that caused a cutoff at `depth`
+pub fn history_bonus(depth: usize) -> i32 {
+    (300 * depth as i32 - 250).clamp(0, 2500)
+}
+
+/// Gravity update: large scores change less, so a score never leaves `-MAX_HISTORY..=MAX_HISTORY`
+fn update(entry: &mut i16, bonus: i32) {
+    let bonus = bonus.clamp(-MAX_HISTORY, MAX_HISTORY);
+    let value = *entry as i32;
+    *entry = (value + bonus - value * bonus.abs() / MAX_HISTORY) as i16;
+}
+
+/// Quiet move statistics collected over searches
+pub struct History {
+    /// Scores indexed by side to move, from and to square of the move
+    butterfly: [[[i16; 64]; 64]; 2],
+    /// Quiet move that refuted a move, indexed by its piece and target square
+    counter_moves: [[Move; 64]; 12],
+    /// Scores indexed by piece and target square of an earlier move followed by those of the move
+    continuation: Box<[[[[i16; 64]; 12]; 64]; 12]>,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl History {
+    pub fn new() -> Self {
+        History {
+            butterfly: [[[0; 64]; 64]; 2],
+            counter_moves: [[Move::NULL; 64]; 12],
+            continuation: vec![[[[0; 64]; 12]; 64]; 12]
+                .into_boxed_slice()
+                .try_into()
+                .expect("continuation history has 12 rows"),
+        }
+    }
+
+    /// Resets all statistics
+    pub fn clear(&mut self) {
+        *self = History::new();
+    }
+
+    /// Halves all scores so the results of the next search weigh more than older ones
+    pub fn age(&mut self) {
+        let halve = |entry: &mut i16| *entry /= 2;
+        self.butterfly
+            .iter_mut()
+            .flatten()
+            .flatten()
+            .for_each(halve);
+        self.continuation
+            .iter_mut()
+            .flatten()
+            .flatten()
+            .flatten()
+            .for_each(halve);
+    }
+
+    /// Returns the quiet move that refuted the previous move before
+    pub fn counter_move(&self, previous: ContinuationKey) -> Move {
+        previous.map_or(Move::NULL, |(piece, to_sq)| {
+            self.counter_moves[piece.get_index()][to_sq as usize]
+        })
+    }
+
+    /// Returns the combined butterfly and continuation score of a quiet move of `piece`, given the
+    /// moves one and two plies before
+    pub fn quiet_score(&self, piece: Piece, mv: Move, previous: [ContinuationKey; 2]) -> i32 {
+        let (from_sq, to_sq) = (mv.get_from() as usize, mv.get_to() as usize);
+        let mut score = self.butterfly[piece.get_color() as usize][from_sq][to_sq] as i32;
+        for (prev_piece, prev_to) in previous.into_iter().flatten() {
+            score += self.continuation[prev_piece.get_index()][prev_to as usize][piece.get_index()]
+                [to_sq] as i32;
+        }
+        score
+    }
+
+    /// Rewards the quiet move that caused a cutoff and penalizes the quiet moves searched before
+    /// it without success
+    pub fn update_quiets(
+        &mut self,
+        position: &Position,
+        best: Move,
+        tried: &[Move],
+        previous: [ContinuationKey; 2],
+        depth: usize,
+    ) {
+        let bonus = history_bonus(depth);
+        self.update_quiet(position, best, previous, bonus);
+        for &mv in tried {
+            self.update_quiet(position, mv, previous, -bonus);
+        }
+
+        if let Some((piece, to_sq)) = previous[0] {
+            self.counter_moves[piece.get_index()][to_sq as usize] = best;
+        }
+    }
+
+    fn update_quiet(
+        &mut self,
+        position: &Position,
+        mv: Move,
+        previous: [ContinuationKey; 2],
+        bonus: i32,
+    ) {
+        let Some(piece) = position.piece_at(mv.get_from()) else {
+            return;
+        };
+        let (from_sq, to_sq) = (mv.get_from() as usize, mv.get_to() as usize);
+        update(
+            &mut self.butterfly[piece.get_color() as usize][from_sq][to_sq],
+            bonus,
+        );
+        for (prev_piece, prev_to) in previous.into_iter().flatten() {
+            update(
+                &mut self.continuation[prev_piece.get_index()][prev_to as usize][piece.get_index()]
+                    [to_sq],
+                bonus,
+            );
+        }
+    }
+}
+
+/// Two quiet moves per ply that caused a cutoff in a sibling node
+pub struct Killers([[Move; 2]; MAX_PLY]);
+
+impl Default for Killers {
+    fn default() -> Self {
+        Killers([[Move::NULL; 2]; MAX_PLY])
+    }
+}
+
+impl Killers {
+    pub fn get(&self, ply: usize) -> [Move; 2] {
+        self.0[ply]
+    }
+
+    /// Makes a move the first killer of its ply, keeping the previous first one
+    pub fn store(&mut self, ply: usize, mv: Move) {
+        let killers = &mut self.0[ply];
+        if killers[0] != mv {
+            killers[1] = killers[0];
+            killers[0] = mv;
+        }
+    }
+
+    /// Forgets the killers of a ply, which stem from a different part of the tree
+    pub fn clear(&mut self, ply: usize) {
+        self.0[ply] = [Move::NULL; 2];
+    }
+}
+
+/// Orders captures by most valuable victim first, then by least valuable attacker
+pub fn mvv_lva(position: &Position, mv: Move) -> i32 {
+    let attacker = position
+        .piece_at(mv.get_from())
+        .map_or(0, |piece| piece.get_piece_type().bits() as i32);
+    let promotion = mv
+        .get_promotion_piece_type()
+        .map_or(0, |piece_type| SEE_VALUES[piece_type.bits() as usize]);
+    (captured_value(position, mv) + promotion) * 8 - attacker
+}
+
+/// Scores moves from the statistics of the search: captures by MVV-LVA with captures losing
+/// material according to SEE deferred, quiet moves by history with the counter move first
+pub struct OrderingScorer<'a> {
+    history: &'a History,
+    previous: [ContinuationKey; 2],
+    counter_move: Move,
+}
+
+impl<'a> OrderingScorer<'a> {
+    /// Creates a scorer for a node reached by the moves `previous`, the last one first
+    pub fn new(history: &'a History, previous: [ContinuationKey; 2]) -> Self {
+        OrderingScorer {
+            history,
+            previous,
+            counter_move: history.counter_move(previous[0]),
+        }
+    }
+}
+
+impl MoveScorer for OrderingScorer<'_> {
+    fn score_capture(&self, position: &Position, mv: Move) -> i32 {
+        let score = mvv_lva(position, mv);
+        if position.see_ge(mv, 0) {
+            score
+        } else {
+            score - BAD_CAPTURE_PENALTY
+        }
+    }
+
+    fn score_quiet(&self, position: &Position, mv: Move) -> i32 {
+        let Some(piece) = position.piece_at(mv.get_from()) else {
+            return 0;
+        };
+        let counter_bonus = if mv == self.counter_move {
+            COUNTER_MOVE_BONUS
+        } else {
+            0
+        };
+        self.history.quiet_score(piece, mv, self.previous) + counter_bonus
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        attacks::init_tables, cmove::MoveFlags, fen::san_to_int, move_picker::MovePicker,
+        piece::PieceTypes, util::Color,
+    };
+
+    use super::*;
+
+    fn mv(from: &str, to: &str, flags: MoveFlags) -> Move {
+        Move::new(
+            san_to_int(from).unwrap().into(),
+            san_to_int(to).unwrap().into(),
+            flags,
+        )
+    }
+
+    #[test]
+    fn saturate_and_age_history_scores() {
+        init_tables();
+        let position = Position::from("4k3/8/8/8/8/8/8/R3K3 w - - 0 1");
+        let rook = Piece::new(Color::White, PieceTypes::ROOK);
+        let (good, bad) = (
+            mv("a1", "a7", MoveFlags::QUIET),
+            mv("a1", "b1", MoveFlags::QUIET),
+        );
+        let previous = [Some((Piece::new(Color::Black, PieceTypes::KING), 60)), None];
+
+        let mut history = History::new();
+        for _ in 0..100 {
+            history.update_quiets(&position, good, &[bad], previous, 20);
+        }
+        let score = history.quiet_score(rook, good, previous);
+        assert!(score > MAX_HISTORY && score <= 2 * MAX_HISTORY);
+        assert!(history.quiet_score(rook, bad, previous) >= -2 * MAX_HISTORY);
+        assert!(history.quiet_score(rook, good, [None; 2]) <= MAX_HISTORY);
+        assert!(history.counter_move(previous[0]) == good);
+
+        history.age();
+        // Both tables are halved separately
+        assert!((history.quiet_score(rook, good, previous) - score / 2).abs() <= 1);
+        history.clear();
+        assert!(history.quiet_score(rook, good, previous) == 0);
+    }
+
+    #[test]
+    fn order_losing_captures_after_quiets() {
+        init_tables();
+        // Rxd5 wins a pawn, Qxb5 loses the queen for a pawn
+        let position = Position::from("4k3/8/p7/1p1p4/8/8/3R4/1Q2K3 w - - 0 1");
+        let history = History::new();
+        let scorer = OrderingScorer::new(&history, [None; 2]);
+        let mut picker = MovePicker::new(&position, Move::NULL, [Move::NULL; 2]);
+
+        let mut picked = Vec::new();
+        while let Some(mv) = picker.next(&position, &scorer) {
+            picked.push(mv);
+        }
+        assert!(picked[0] == mv("d2", "d5", MoveFlags::CAPTURE));
+        assert!(picked[picked.len() - 1] == mv("b1", "b5", MoveFlags::CAPTURE));
+        assert!(scorer.score_capture(&position, picked[0]) > 0);
+    }
+
+    #[test]
+    fn order_counter_moves_and_history_among_quiets() {
+        init_tables();
+        let position = Position::from("4k3/8/8/8/8/8/8/R3K3 w - - 0 1");
+        let previous = [Some((Piece::new(Color::Black, PieceTypes::KING), 60)), None];
+        let counter = mv("a1", "a2", MoveFlags::QUIET);
+        let good = mv("a1", "a7", MoveFlags::QUIET);
+
+        let mut history = History::new();
+        history.update_quiets(&position, good, &[], [None; 2], 8);
+        history.update_quiets(&position, counter, &[], previous, 1);
+
+        let scorer = OrderingScorer::new(&history, previous);
+        assert!(scorer.score_quiet(&position, counter) > scorer.score_quiet(&position, good));
+        assert!(scorer.score_quiet(&position, good) > 0);
+
+        // Without the previous move only the butterfly scores count
+        let scorer = OrderingScorer::new(&history, [None; 2]);
+        assert!(scorer.score_quiet(&position, good) > scorer.score_quiet(&position, counter));
+    }
+}
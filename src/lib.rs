@@ -0,0 +1,25 @@
+pub mod attacks;
+pub mod book;
+pub mod castling_rights;
+pub mod cmove;
+pub mod datagen;
+pub mod evaluation;
+pub mod fen;
+pub mod history;
+pub mod limits;
+pub mod move_list;
+pub mod move_picker;
+pub mod movegen;
+pub mod nnue;
+pub mod perft;
+pub mod piece;
+pub mod position;
+pub mod search;
+pub mod see;
+pub mod transposition_table;
+pub mod tuner;
+pub mod uci;
+pub mod util;
+pub mod zobrist;
+
+use util::*;
@@ -0,0 +1,133 @@
+use std::time::Duration;
+
+use crate::util::Color;
+
+/// Limits of a search as given by the UCI `go` command
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchLimits {
+    pub depth: Option<usize>,
+    pub nodes: Option<u64>,
+    pub mate: Option<usize>,
+    pub movetime: Option<u64>,
+    pub wtime: Option<u64>,
+    pub btime: Option<u64>,
+    pub winc: Option<u64>,
+    pub binc: Option<u64>,
+    pub movestogo: Option<u64>,
+    pub infinite: bool,
+    pub searchmoves: Vec<String>,
+}
+
+/// Time kept in reserve to cover the communication overhead with the GUI
+const MOVE_OVERHEAD: u64 = 30;
+
+impl SearchLimits {
+    /// Parses the arguments of the UCI `go` command. Unknown tokens are ignored.
+    pub fn parse(args: &str) -> Self {
+        let mut limits = SearchLimits::default();
+        let mut tokens = args.split_whitespace().peekable();
+
+        while let Some(token) = tokens.next() {
+            let mut number = || tokens.next().and_then(|value| value.parse::<u64>().ok());
+            match token {
+                "depth" => limits.depth = number().map(|depth| depth as usize),
+                "nodes" => limits.nodes = number(),
+                // There is no mate in zero moves to look for
+                "mate" => {
+                    limits.mate = number()
+                        .filter(|&moves| moves > 0)
+                        .map(|moves| moves as usize)
+                }
+                "movetime" => limits.movetime = number(),
+                "wtime" => limits.wtime = number(),
+                "btime" => limits.btime = number(),
+                "winc" => limits.winc = number(),
+                "binc" => limits.binc = number(),
+                "movestogo" => limits.movestogo = number(),
+                "infinite" => limits.infinite = true,
+                "searchmoves" => {
+                    while let Some(mv) = tokens.next_if(|token| is_uci_move(token)) {
+                        limits.searchmoves.push(mv.to_string());
+                    }
+                }
+                _ => (),
+            }
+        }
+        limits
+    }
+
+    /// Returns the time the side to move may spend on this move, if the search is timed at all
+    pub fn time_budget(&self, color: Color) -> Option<Duration> {
+        if self.infinite {
+            return None;
+        }
+        if let Some(movetime) = self.movetime {
+            return Some(Duration::from_millis(
+                movetime.saturating_sub(MOVE_OVERHEAD).max(1),
+            ));
+        }
+
+        let (time, inc) = match color {
+            Color::White => (self.wtime?, self.winc.unwrap_or(0)),
+            Color::Black => (self.btime?, self.binc.unwrap_or(0)),
+        };
+        let moves_to_go = self.movestogo.unwrap_or(30).clamp(1, 30);
+        let time_left = time.saturating_sub(MOVE_OVERHEAD);
+
+        // Spend an even share of the remaining time plus most of the increment, but never more
+        // than what is left on the clock
+        let budget = (time_left / moves_to_go + inc * 3 / 4).min(time_left * 4 / 5);
+        Some(Duration::from_millis(budget.max(1)))
+    }
+}
+
+/// Checks if a token looks like a move in long algebraic notation, e.g. `e2e4` or `a7a8q`
+fn is_uci_move(token: &str) -> bool {
+    let bytes = token.as_bytes();
+    (bytes.len() == 4 || bytes.len() == 5)
+        && (b'a'..=b'h').contains(&bytes[0])
+        && (b'1'..=b'8').contains(&bytes[1])
+        && (b'a'..=b'h').contains(&bytes[2])
+        && (b'1'..=b'8').contains(&bytes[3])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_go_arguments_correctly() {
+        let limits =
+            SearchLimits::parse("wtime 60000 btime 50000 winc 1000 binc 1000 movestogo 20");
+        assert!(limits.wtime == Some(60000));
+        assert!(limits.btime == Some(50000));
+        assert!(limits.winc == Some(1000));
+        assert!(limits.movestogo == Some(20));
+        assert!(!limits.infinite);
+
+        let limits = SearchLimits::parse("searchmoves e2e4 d2d4 depth 12 infinite");
+        assert!(limits.searchmoves == vec!["e2e4", "d2d4"]);
+        assert!(limits.depth == Some(12));
+        assert!(limits.infinite);
+
+        assert!(SearchLimits::parse("mate 3").mate == Some(3));
+        assert!(SearchLimits::parse("mate 0").mate.is_none());
+    }
+
+    #[test]
+    fn allocate_time_correctly() {
+        let limits = SearchLimits::parse("movetime 1000");
+        assert!(limits.time_budget(Color::White) == Some(Duration::from_millis(970)));
+
+        let limits = SearchLimits::parse("wtime 60030 btime 1000 winc 0 binc 0 movestogo 20");
+        assert!(limits.time_budget(Color::White) == Some(Duration::from_millis(3000)));
+        assert!(limits.time_budget(Color::Black).unwrap() < Duration::from_millis(100));
+
+        assert!(SearchLimits::parse("depth 5")
+            .time_budget(Color::White)
+            .is_none());
+        assert!(SearchLimits::parse("infinite")
+            .time_budget(Color::White)
+            .is_none());
+    }
+}
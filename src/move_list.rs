@@ -0,0 +1,160 @@
+use std::ops::Index;
+
+use crate::cmove::Move;
+
+/// Capacity of a move list, a safe upper bound above the known maximum of 218 legal moves in any
+/// reachable position
+pub const MAX_MOVES: usize = 256;
+
+/// Fixed-capacity list of moves with a score per move, living entirely on the stack
+#[derive(Clone)]
+pub struct MoveList {
+    moves: [Move; MAX_MOVES],
+    scores: [i32; MAX_MOVES],
+    len: usize,
+}
+
+impl Default for MoveList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MoveList {
+    /// Creates an empty move list
+    pub fn new() -> Self {
+        MoveList {
+            moves: [Move::NULL; MAX_MOVES],
+            scores: [0; MAX_MOVES],
+            len: 0,
+        }
+    }
+
+    /// Appends a move with a score of zero
+    pub fn push(&mut self, mv: Move) {
+        debug_assert!(self.len < MAX_MOVES, "move list overflow");
+        self.moves[self.len] = mv;
+        self.scores[self.len] = 0;
+        self.len += 1;
+    }
+
+    /// Returns the number of moves in the list
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Checks if the list contains no moves
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Removes all moves from the list
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    /// Iterates over the moves in the list
+    pub fn iter(&self) -> std::slice::Iter<'_, Move> {
+        self.moves[..self.len].iter()
+    }
+
+    /// Checks if the list contains the given move
+    pub fn contains(&self, mv: Move) -> bool {
+        self.moves[..self.len].contains(&mv)
+    }
+
+    /// Returns the score of the move at the given index
+    pub fn get_score(&self, idx: usize) -> i32 {
+        self.scores[..self.len][idx]
+    }
+
+    /// Sets the score of the move at the given index
+    pub fn set_score(&mut self, idx: usize, score: i32) {
+        self.scores[..self.len][idx] = score;
+    }
+
+    /// Removes the move at the given index and replaces it with the last move of the list
+    pub fn swap_remove(&mut self, idx: usize) -> Move {
+        assert!(idx < self.len, "swap_remove index out of bounds");
+        let mv = self.moves[idx];
+        self.len -= 1;
+        self.moves[idx] = self.moves[self.len];
+        self.scores[idx] = self.scores[self.len];
+        mv
+    }
+
+    /// Sorts the moves by descending score, keeping the generation order for equal scores
+    pub fn sort_by_score(&mut self) {
+        // Insertion sort is stable and fast for the short lists seen in practice
+        for idx in 1..self.len {
+            let mv = self.moves[idx];
+            let score = self.scores[idx];
+            let mut pos = idx;
+            while pos > 0 && self.scores[pos - 1] < score {
+                self.moves[pos] = self.moves[pos - 1];
+                self.scores[pos] = self.scores[pos - 1];
+                pos -= 1;
+            }
+            self.moves[pos] = mv;
+            self.scores[pos] = score;
+        }
+    }
+}
+
+impl Index<usize> for MoveList {
+    type Output = Move;
+
+    fn index(&self, idx: usize) -> &Move {
+        &self.moves[..self.len][idx]
+    }
+}
+
+impl<'a> IntoIterator for &'a MoveList {
+    type Item = &'a Move;
+    type IntoIter = std::slice::Iter<'a, Move>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cmove::MoveFlags;
+
+    use super::*;
+
+    #[test]
+    fn push_and_remove_moves_correctly() {
+        let mut moves = MoveList::new();
+        assert!(moves.is_empty());
+
+        moves.push(Move::new(12, 28, MoveFlags::DOUBLE_PAWN_PUSH));
+        moves.push(Move::new(6, 21, MoveFlags::QUIET));
+        moves.push(Move::new(1, 18, MoveFlags::QUIET));
+        assert!(moves.len() == 3);
+        assert!(moves.contains(Move::new(6, 21, MoveFlags::QUIET)));
+        assert!(!moves.contains(Move::new(6, 21, MoveFlags::CAPTURE)));
+
+        let removed = moves.swap_remove(0);
+        assert!(removed == Move::new(12, 28, MoveFlags::DOUBLE_PAWN_PUSH));
+        assert!(moves.len() == 2);
+        assert!(moves[0] == Move::new(1, 18, MoveFlags::QUIET));
+        assert!(!moves.contains(removed));
+    }
+
+    #[test]
+    fn sort_moves_by_score_correctly() {
+        let mut moves = MoveList::new();
+        for (to, score) in [(16, 10), (17, -5), (18, 30), (19, 10)] {
+            moves.push(Move::new(8, to, MoveFlags::QUIET));
+            moves.set_score(moves.len() - 1, score);
+        }
+        moves.sort_by_score();
+
+        let order: Vec<u8> = moves.iter().map(|mv| mv.get_to()).collect();
+        assert!(order == vec![18, 16, 19, 17]);
+        assert!(moves.get_score(0) == 30);
+        assert!(moves.get_score(3) == -5);
+    }
+}
@@ -0,0 +1,321 @@
+use crate::{cmove::Move, move_list::MoveList, movegen::GenType, position::Position};
+
+/// Score offset that puts every capture ahead of the quiet moves while evading a check
+const EVASION_CAPTURE_BONUS: i32 = 1 << 24;
+
+/// Assigns ordering scores to generated moves, higher scores are tried first
+pub trait MoveScorer {
+    /// Scores a capture or promotion. Captures scored below zero are deferred until after the quiets.
+    fn score_capture(&self, _position: &Position, _mv: Move) -> i32 {
+        0
+    }
+
+    /// Scores a quiet move
+    fn score_quiet(&self, _position: &Position, _mv: Move) -> i32 {
+        0
+    }
+}
+
+/// Scorer that keeps the generation order
+impl MoveScorer for () {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Stage {
+    TtMove,
+    GenCaptures,
+    GoodCaptures,
+    GenQuiets,
+    Killers,
+    Quiets,
+    BadCaptures,
+    EvasionTtMove,
+    GenEvasions,
+    Evasions,
+    Done,
+}
+
+/// Yields the legal moves of a position stage by stage: the transposition table move first, then
+/// captures, killers, quiet moves and finally losing captures. Moves of a stage are only generated
+/// once the previous stages are exhausted, so a cutoff early on saves the remaining generation.
+pub struct MovePicker {
+    stage: Stage,
+    tt_move: Move,
+    killers: [Move; 2],
+    killer_idx: usize,
+    skip_quiets: bool,
+    moves: MoveList,
+    bad_captures: MoveList,
+}
+
+impl MovePicker {
+    /// Creates a move picker for the main search
+    pub fn new(position: &Position, tt_move: Move, killers: [Move; 2]) -> Self {
+        let in_check = position.in_check();
+        let tt_move = if position.is_legal(tt_move) {
+            tt_move
+        } else {
+            Move::NULL
+        };
+
+        MovePicker {
+            stage: if in_check {
+                Stage::EvasionTtMove
+            } else {
+                Stage::TtMove
+            },
+            tt_move,
+            killers,
+            killer_idx: 0,
+            skip_quiets: false,
+            moves: MoveList::new(),
+            bad_captures: MoveList::new(),
+        }
+    }
+
+    /// Creates a move picker for the quiescence search that only yields captures and promotions.
+    /// When in check all evasions are yielded instead if `evasions` is set.
+    pub fn new_qsearch(position: &Position, tt_move: Move, evasions: bool) -> Self {
+        let tactical_tt_move = if tt_move.is_capture() || tt_move.is_promotion() {
+            tt_move
+        } else {
+            Move::NULL
+        };
+        let mut picker = Self::new(position, tactical_tt_move, [Move::NULL; 2]);
+        picker.skip_quiets = true;
+        if !evasions {
+            picker.stage = Stage::TtMove;
+        }
+        picker
+    }
+
+    /// Stops yielding quiet moves that have not been tried yet, evasions are not affected
+    pub fn skip_quiets(&mut self) {
+        self.skip_quiets = true;
+    }
+
+    /// Returns the next move to search or `None` once all moves have been yielded
+    pub fn next(&mut self, position: &Position, scorer: &impl MoveScorer) -> Option<Move> {
+        loop {
+            match self.stage {
+                Stage::TtMove => {
+                    self.stage = Stage::GenCaptures;
+                    if self.tt_move != Move::NULL {
+                        return Some(self.tt_move);
+                    }
+                }
+                Stage::GenCaptures => {
+                    position.generate(GenType::Captures, &mut self.moves);
+                    self.remove_tt_move();
+                    for idx in 0..self.moves.len() {
+                        let score = scorer.score_capture(position, self.moves[idx]);
+                        self.moves.set_score(idx, score);
+                    }
+                    self.stage = Stage::GoodCaptures;
+                }
+                Stage::GoodCaptures => match pick_best(&mut self.moves) {
+                    Some((mv, score)) if score < 0 => {
+                        self.bad_captures.push(mv);
+                        let last = self.bad_captures.len() - 1;
+                        self.bad_captures.set_score(last, score);
+                    }
+                    Some((mv, _)) => return Some(mv),
+                    None => self.stage = Stage::GenQuiets,
+                },
+                Stage::GenQuiets => {
+                    if self.skip_quiets {
+                        self.stage = Stage::BadCaptures;
+                        continue;
+                    }
+                    position.generate(GenType::Quiets, &mut self.moves);
+                    self.remove_tt_move();
+                    self.stage = Stage::Killers;
+                }
+                Stage::Killers => {
+                    if self.skip_quiets || self.killer_idx >= self.killers.len() {
+                        self.stage = Stage::Quiets;
+                        self.score_quiets(position, scorer);
+                        continue;
+                    }
+                    let killer = self.killers[self.killer_idx];
+                    self.killer_idx += 1;
+
+                    // Killers come from sibling nodes and have to be legal quiet moves here
+                    if let Some(idx) = self.moves.iter().position(|&mv| mv == killer) {
+                        return Some(self.moves.swap_remove(idx));
+                    }
+                }
+                Stage::Quiets => {
+                    if !self.skip_quiets {
+                        if let Some((mv, _)) = pick_best(&mut self.moves) {
+                            return Some(mv);
+                        }
+                    }
+                    self.stage = Stage::BadCaptures;
+                }
+                Stage::BadCaptures => match pick_best(&mut self.bad_captures) {
+                    Some((mv, _)) => return Some(mv),
+                    None => self.stage = Stage::Done,
+                },
+                Stage::EvasionTtMove => {
+                    self.stage = Stage::GenEvasions;
+                    if self.tt_move != Move::NULL {
+                        return Some(self.tt_move);
+                    }
+                }
+                Stage::GenEvasions => {
+                    position.generate(GenType::Evasions, &mut self.moves);
+                    self.remove_tt_move();
+                    for idx in 0..self.moves.len() {
+                        let mv = self.moves[idx];
+                        let score = if mv.is_capture() || mv.is_promotion() {
+                            scorer.score_capture(position, mv) + EVASION_CAPTURE_BONUS
+                        } else {
+                            scorer.score_quiet(position, mv)
+                        };
+                        self.moves.set_score(idx, score);
+                    }
+                    self.stage = Stage::Evasions;
+                }
+                Stage::Evasions => match pick_best(&mut self.moves) {
+                    Some((mv, _)) => return Some(mv),
+                    None => self.stage = Stage::Done,
+                },
+                Stage::Done => return None,
+            }
+        }
+    }
+
+    /// Removes the already yielded transposition table move from the current stage
+    fn remove_tt_move(&mut self) {
+        if let Some(idx) = self.moves.iter().position(|&mv| mv == self.tt_move) {
+            self.moves.swap_remove(idx);
+        }
+    }
+
+    fn score_quiets(&mut self, position: &Position, scorer: &impl MoveScorer) {
+        for idx in 0..self.moves.len() {
+            let score = scorer.score_quiet(position, self.moves[idx]);
+            self.moves.set_score(idx, score);
+        }
+    }
+}
+
+/// Removes and returns the highest scored move of the list
+fn pick_best(moves: &mut MoveList) -> Option<(Move, i32)> {
+    if moves.is_empty() {
+        return None;
+    }
+    let mut best_idx = 0;
+    for idx in 1..moves.len() {
+        if moves.get_score(idx) > moves.get_score(best_idx) {
+            best_idx = idx;
+        }
+    }
+    let score = moves.get_score(best_idx);
+    Some((moves.swap_remove(best_idx), score))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{attacks::init_tables, cmove::MoveFlags, fen::san_to_int};
+
+    use super::*;
+
+    const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+
+    fn mv(from: &str, to: &str, flags: MoveFlags) -> Move {
+        Move::new(
+            san_to_int(from).unwrap().into(),
+            san_to_int(to).unwrap().into(),
+            flags,
+        )
+    }
+
+    fn pick_all(position: &Position, picker: &mut MovePicker) -> Vec<Move> {
+        let mut moves = Vec::new();
+        while let Some(mv) = picker.next(position, &()) {
+            moves.push(mv);
+        }
+        moves
+    }
+
+    #[test]
+    fn yield_every_legal_move_once() {
+        init_tables();
+        for fen in [
+            KIWIPETE,
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "8/8/8/2k5/3Pp3/8/8/4K3 b - d3 0 1",
+        ] {
+            let position = Position::from(fen);
+            let legal = position.generate_moves();
+            let tt_move = legal[legal.len() / 2];
+            let mut picker = MovePicker::new(&position, tt_move, [Move::NULL; 2]);
+            let picked = pick_all(&position, &mut picker);
+
+            assert!(picked[0] == tt_move);
+            assert!(picked.len() == legal.len());
+            assert!(legal.iter().all(|mv| picked.contains(mv)));
+        }
+    }
+
+    #[test]
+    fn yield_captures_before_killers_before_quiets() {
+        init_tables();
+        let position = Position::from(KIWIPETE);
+        let killer = mv("a2", "a3", MoveFlags::QUIET);
+        let illegal_killer = mv("a2", "a4", MoveFlags::QUIET);
+        let mut picker = MovePicker::new(&position, Move::NULL, [illegal_killer, killer]);
+        let picked = pick_all(&position, &mut picker);
+
+        let captures = picked.iter().take_while(|mv| mv.is_capture()).count();
+        assert!(captures == 8);
+        assert!(picked[captures] == killer);
+        assert!(!picked.contains(&illegal_killer));
+        assert!(picked[captures + 1..].iter().all(|mv| !mv.is_capture()));
+    }
+
+    #[test]
+    fn skip_illegal_tt_move_and_quiets_in_qsearch() {
+        init_tables();
+        let position = Position::from(KIWIPETE);
+        let mut picker =
+            MovePicker::new_qsearch(&position, mv("e1", "e2", MoveFlags::CAPTURE), true);
+        let picked = pick_all(&position, &mut picker);
+
+        assert!(picked.len() == 8);
+        assert!(picked.iter().all(|mv| mv.is_capture()));
+    }
+
+    #[test]
+    fn yield_evasions_in_qsearch_only_if_requested() {
+        init_tables();
+        let position = Position::from("4k3/8/8/8/8/8/3q4/4K3 w - - 0 1");
+
+        let mut picker = MovePicker::new_qsearch(&position, Move::NULL, true);
+        assert!(pick_all(&position, &mut picker).len() == 2);
+
+        let mut picker = MovePicker::new_qsearch(&position, Move::NULL, false);
+        assert!(pick_all(&position, &mut picker) == vec![mv("e1", "d2", MoveFlags::CAPTURE)]);
+    }
+
+    #[test]
+    fn generate_quiet_checks_correctly() {
+        init_tables();
+        let quiet_checks = |fen: &str| {
+            let mut moves = MoveList::new();
+            Position::from(fen).generate(GenType::QuietChecks, &mut moves);
+            let mut checks: Vec<String> = moves.iter().map(|mv| mv.to_string()).collect();
+            checks.sort();
+            checks
+        };
+
+        assert!(
+            quiet_checks("3k4/8/8/8/8/8/3B4/R3K2R w KQ - 0 1") == ["a1a8", "d2a5", "d2g5", "h1h8"]
+        );
+        // Every bishop move uncovers the rook
+        assert!(quiet_checks("3k4/8/8/8/8/8/3B4/3RK3 w - - 0 1").len() == 8);
+        // The rook gives check after castling
+        assert!(quiet_checks("5k2/8/8/8/8/8/8/4K2R w K - 0 1") == ["e1g1", "h1f1", "h1h8"]);
+    }
+}
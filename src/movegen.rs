@@ -0,0 +1,529 @@
+use crate::{
+    attacks::{
+        lookup_between, lookup_bishop_att, lookup_king_att, lookup_knight_att, lookup_line,
+        lookup_pawn_att, lookup_piece_att, lookup_queen_att, lookup_rook_att,
+    },
+    castling_rights::CastlingRights,
+    cmove::{Move, MoveFlags},
+    move_list::MoveList,
+    piece::{Piece, PieceTypes},
+    position::Position,
+    util::{bb_from_square, enumerate_bits, opp, relative_rank, Bitboard, Color},
+};
+
+/// Selects which subset of the legal moves gets generated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GenType {
+    /// Captures, en passant captures and all promotions
+    Captures,
+    /// Non-capturing moves without promotions, including castling
+    Quiets,
+    /// All legal moves while the side to move is in check
+    Evasions,
+    /// Quiet moves that give check
+    QuietChecks,
+    /// All legal moves
+    All,
+}
+
+impl Position {
+    /// Generates all legal moves in the position
+    pub fn generate_moves(&self) -> MoveList {
+        let mut moves = MoveList::new();
+        self.generate(GenType::All, &mut moves);
+        moves
+    }
+
+    /// Appends the legal moves of the given generation stage to the move list
+    pub fn generate(&self, gen_type: GenType, moves: &mut MoveList) {
+        if gen_type == GenType::QuietChecks {
+            let mut quiets = MoveList::new();
+            self.generate(GenType::Quiets, &mut quiets);
+            for &mv in quiets.iter() {
+                if self.gives_check(mv) {
+                    moves.push(mv);
+                }
+            }
+            return;
+        }
+
+        let ally_color = self.side_to_move();
+        let opp_color = opp(ally_color);
+
+        let ally_pieces_bb = self.all_pieces_bb(ally_color.into());
+        let opp_pieces_bb = self.all_pieces_bb(opp_color.into());
+        let all_pieces_bb = ally_pieces_bb | opp_pieces_bb;
+
+        let king_sq = self.king_square(ally_color);
+        let checkers = self.attackers_to(king_sq, all_pieces_bb) & opp_pieces_bb;
+        let pinned = self.pinned_pieces(ally_color);
+
+        debug_assert!(gen_type != GenType::Evasions || checkers != 0);
+
+        let gen_captures = gen_type != GenType::Quiets;
+        let gen_quiets = gen_type != GenType::Captures;
+
+        // Squares pieces other than pawns may move to in this stage
+        let target_mask = match gen_type {
+            GenType::Captures => opp_pieces_bb,
+            GenType::Quiets => !all_pieces_bb,
+            _ => !ally_pieces_bb,
+        };
+
+        // King moves
+        // The king is removed from the occupancy so it cannot hide behind itself on a slider ray
+        let occupancy_without_king = all_pieces_bb & !bb_from_square(king_sq);
+        let is_attacked =
+            |square: u8| self.attackers_to(square, occupancy_without_king) & opp_pieces_bb != 0;
+        let mut king_targets = 0;
+        enumerate_bits(lookup_king_att(king_sq) & target_mask, |to_sq| {
+            if !is_attacked(to_sq) {
+                king_targets |= bb_from_square(to_sq);
+            }
+        });
+        push_moves(moves, king_sq, king_targets, opp_pieces_bb);
+
+        // Castling
+        let (king_side, queen_side, home_sq) = match ally_color {
+            Color::White => (
+                CastlingRights::WHITE_KING_SIDE,
+                CastlingRights::WHITE_QUEEN_SIDE,
+                4,
+            ),
+            Color::Black => (
+                CastlingRights::BLACK_KING_SIDE,
+                CastlingRights::BLACK_QUEEN_SIDE,
+                60,
+            ),
+        };
+        if gen_quiets && checkers == 0 && king_sq == home_sq {
+            let rook_bb = self.piece_bb(ally_color, PieceTypes::ROOK);
+
+            for (right, rook_sq, to_sq, flag) in [
+                (king_side, king_sq + 3, king_sq + 2, MoveFlags::KING_CASTLE),
+                (
+                    queen_side,
+                    king_sq - 4,
+                    king_sq - 2,
+                    MoveFlags::QUEEN_CASTLE,
+                ),
+            ] {
+                if !self.castling_rights().contains(right)
+                    || rook_bb & bb_from_square(rook_sq) == 0
+                    || lookup_between(king_sq, rook_sq) & all_pieces_bb != 0
+                {
+                    continue;
+                }
+
+                // The king may not pass through or land on an attacked square
+                let mut path_attacked = false;
+                enumerate_bits(
+                    lookup_between(king_sq, to_sq) | bb_from_square(to_sq),
+                    |square| path_attacked |= is_attacked(square),
+                );
+
+                if !path_attacked {
+                    moves.push(Move::new(king_sq.into(), to_sq.into(), flag));
+                }
+            }
+        }
+
+        // In double check only the king can move
+        if checkers.count_ones() > 1 {
+            return;
+        }
+
+        // Squares a non-king move has to land on: capturing the checker or blocking the check
+        let check_mask = if checkers != 0 {
+            checkers | lookup_between(king_sq, checkers.trailing_zeros() as u8)
+        } else {
+            !0
+        };
+
+        // Squares a piece may move to without exposing the king
+        let pin_mask = |from_sq: u8| {
+            if pinned & bb_from_square(from_sq) != 0 {
+                lookup_line(king_sq, from_sq)
+            } else {
+                !0
+            }
+        };
+
+        let knight = Piece::new(ally_color, PieceTypes::KNIGHT);
+
+        // Knight moves
+        enumerate_bits(self.pieces[knight.get_index()] & !pinned, |from_sq| {
+            let att = lookup_knight_att(from_sq) & target_mask & check_mask;
+            push_moves(moves, from_sq, att, opp_pieces_bb);
+        });
+
+        // Pawn moves
+        let pawn = Piece::new(ally_color, PieceTypes::PAWN);
+        let pawn_direction: i8 = if ally_color == Color::White { 1 } else { -1 };
+        let prom_rank = relative_rank(7, ally_color);
+
+        enumerate_bits(self.pieces[pawn.get_index()], |from_sq| {
+            let legal_mask = check_mask & pin_mask(from_sq);
+            let to_sq = (from_sq as i8 + 8 * pawn_direction) as u8;
+            let to_bb = bb_from_square(to_sq);
+            let from_bb = bb_from_square(from_sq);
+
+            if to_bb & !all_pieces_bb != 0 {
+                if to_bb & legal_mask != 0 {
+                    // Pawn promotions
+                    if to_bb & prom_rank != 0 {
+                        if gen_captures {
+                            push_promotions(moves, from_sq, to_sq, false);
+                        }
+                    }
+                    // Pawn single push
+                    else if gen_quiets {
+                        moves.push(Move::new(from_sq.into(), to_sq.into(), MoveFlags::QUIET));
+                    }
+                }
+
+                // Pawn double push
+                let to_sq = (to_sq as i8 + 8 * pawn_direction) as u8;
+                let to_bb = bb_from_square(to_sq);
+                if gen_quiets
+                    && to_bb & !all_pieces_bb & legal_mask != 0
+                    && from_bb & relative_rank(1, ally_color) != 0
+                {
+                    moves.push(Move::new(
+                        from_sq.into(),
+                        to_sq.into(),
+                        MoveFlags::DOUBLE_PAWN_PUSH,
+                    ));
+                }
+            }
+
+            if !gen_captures {
+                return;
+            }
+
+            let att = lookup_pawn_att(from_sq, ally_color) & opp_pieces_bb & legal_mask;
+            let captures = att & !prom_rank;
+            let prom_captures = att & prom_rank;
+
+            // Capture pawn moves
+            enumerate_bits(captures, |to_sq| {
+                moves.push(Move::new(from_sq.into(), to_sq.into(), MoveFlags::CAPTURE))
+            });
+
+            // Promotion capture pawn moves
+            enumerate_bits(prom_captures, |to_sq| {
+                push_promotions(moves, from_sq, to_sq, true);
+            });
+        });
+
+        // En passant captures
+        if let (true, Some(ep_sq)) = (gen_captures, self.en_passant_square()) {
+            let ep_bb = bb_from_square(ep_sq);
+            let victim_sq = (ep_sq as i8 - 8 * pawn_direction) as u8;
+            let victim_bb = bb_from_square(victim_sq);
+            let opp_queens = self.piece_bb(opp_color, PieceTypes::QUEEN);
+            let opp_rooks_queens = self.piece_bb(opp_color, PieceTypes::ROOK) | opp_queens;
+            let opp_bishops_queens = self.piece_bb(opp_color, PieceTypes::BISHOP) | opp_queens;
+
+            // Resolves a check either by capturing the checking pawn or by blocking on the ep square
+            let evades_check = (ep_bb & check_mask != 0) || (victim_bb & checkers != 0);
+
+            enumerate_bits(
+                lookup_pawn_att(ep_sq, opp_color) & self.pieces[pawn.get_index()],
+                |from_sq| {
+                    // Both pawns leave their squares at once, which can expose the king to a
+                    // slider, e.g. along the rank when both pawns stood between king and rook
+                    let occupancy = (all_pieces_bb ^ bb_from_square(from_sq) ^ victim_bb) | ep_bb;
+                    let exposed = lookup_rook_att(king_sq, occupancy) & opp_rooks_queens != 0
+                        || lookup_bishop_att(king_sq, occupancy) & opp_bishops_queens != 0;
+
+                    if evades_check && !exposed {
+                        moves.push(Move::new(
+                            from_sq.into(),
+                            ep_sq.into(),
+                            MoveFlags::EP_CAPTURE,
+                        ));
+                    }
+                },
+            );
+        }
+
+        let queen = Piece::new(ally_color, PieceTypes::QUEEN);
+
+        // Queen moves
+        enumerate_bits(self.pieces[queen.get_index()], |from_sq| {
+            let att = lookup_queen_att(from_sq, all_pieces_bb)
+                & target_mask
+                & check_mask
+                & pin_mask(from_sq);
+            push_moves(moves, from_sq, att, opp_pieces_bb);
+        });
+
+        let bishop = Piece::new(ally_color, PieceTypes::BISHOP);
+
+        // Bishop moves
+        enumerate_bits(self.pieces[bishop.get_index()], |from_sq| {
+            let att = lookup_bishop_att(from_sq, all_pieces_bb)
+                & target_mask
+                & check_mask
+                & pin_mask(from_sq);
+            push_moves(moves, from_sq, att, opp_pieces_bb);
+        });
+
+        let rook = Piece::new(ally_color, PieceTypes::ROOK);
+
+        // Rook moves
+        enumerate_bits(self.pieces[rook.get_index()], |from_sq| {
+            let att = lookup_rook_att(from_sq, all_pieces_bb)
+                & target_mask
+                & check_mask
+                & pin_mask(from_sq);
+            push_moves(moves, from_sq, att, opp_pieces_bb);
+        });
+    }
+
+    /// Checks if a move is legal in the position, e.g. a move taken from the transposition table
+    pub fn is_legal(&self, mv: Move) -> bool {
+        if mv == Move::NULL {
+            return false;
+        }
+        let gen_type = if mv.is_capture() || mv.is_promotion() {
+            GenType::Captures
+        } else {
+            GenType::Quiets
+        };
+        let mut moves = MoveList::new();
+        self.generate(gen_type, &mut moves);
+        moves.contains(mv)
+    }
+
+    /// Finds the legal move matching a move in long algebraic notation, e.g. `e7e8q`
+    pub fn parse_move(&self, uci_move: &str) -> Option<Move> {
+        self.generate_moves()
+            .iter()
+            .copied()
+            .find(|mv| mv.to_string() == uci_move)
+    }
+
+    /// Checks if a legal move puts the opponent's king in check
+    pub fn gives_check(&self, mv: Move) -> bool {
+        let ally_color = self.side_to_move();
+        let opp_king_bb = self.piece_bb(opp(ally_color), PieceTypes::KING);
+        let opp_king_sq = opp_king_bb.trailing_zeros() as u8;
+        let from_sq = mv.get_from();
+        let to_sq = mv.get_to();
+        let flags = mv.get_flags();
+
+        let Some(piece) = self.piece_at(from_sq) else {
+            return false;
+        };
+        let piece_type = mv
+            .get_promotion_piece_type()
+            .unwrap_or(piece.get_piece_type());
+
+        let mut moved_bb = bb_from_square(from_sq) | bb_from_square(to_sq);
+        let mut occupancy =
+            (self.all_pieces_bb(None) & !bb_from_square(from_sq)) | bb_from_square(to_sq);
+        if flags == MoveFlags::EP_CAPTURE {
+            occupancy &= !bb_from_square(if ally_color == Color::White {
+                to_sq - 8
+            } else {
+                to_sq + 8
+            });
+        }
+
+        // Direct check by the moved piece
+        if lookup_piece_att(piece_type, to_sq, ally_color, occupancy) & opp_king_bb != 0 {
+            return true;
+        }
+
+        // Check by the rook after castling
+        if flags == MoveFlags::KING_CASTLE || flags == MoveFlags::QUEEN_CASTLE {
+            let (rook_from, rook_to) = if flags == MoveFlags::KING_CASTLE {
+                (to_sq + 1, to_sq - 1)
+            } else {
+                (to_sq - 2, to_sq + 1)
+            };
+            occupancy = (occupancy & !bb_from_square(rook_from)) | bb_from_square(rook_to);
+            moved_bb |= bb_from_square(rook_from);
+            if lookup_rook_att(rook_to, occupancy) & opp_king_bb != 0 {
+                return true;
+            }
+        }
+
+        // Discovered check by a slider behind the moved piece
+        let queens = self.piece_bb(ally_color, PieceTypes::QUEEN);
+        let bishops_queens = (self.piece_bb(ally_color, PieceTypes::BISHOP) | queens) & !moved_bb;
+        let rooks_queens = (self.piece_bb(ally_color, PieceTypes::ROOK) | queens) & !moved_bb;
+        (lookup_bishop_att(opp_king_sq, occupancy) & bishops_queens)
+            | (lookup_rook_att(opp_king_sq, occupancy) & rooks_queens)
+            != 0
+    }
+}
+
+/// Adds a quiet move or capture from a square to every target square
+fn push_moves(moves: &mut MoveList, from_sq: u8, targets: Bitboard, opp_pieces_bb: Bitboard) {
+    enumerate_bits(targets & !opp_pieces_bb, |to_sq| {
+        moves.push(Move::new(from_sq.into(), to_sq.into(), MoveFlags::QUIET));
+    });
+    enumerate_bits(targets & opp_pieces_bb, |to_sq| {
+        moves.push(Move::new(from_sq.into(), to_sq.into(), MoveFlags::CAPTURE));
+    });
+}
+
+/// Adds all four promotions of a pawn move
+fn push_promotions(moves: &mut MoveList, from_sq: u8, to_sq: u8, capture: bool) {
+    let flags = if capture {
+        [
+            MoveFlags::QUEEN_PROM_CAPTURE,
+            MoveFlags::ROOK_PROM_CAPTURE,
+            MoveFlags::BISHOP_PROM_CAPTURE,
+            MoveFlags::KNIGHT_PROM_CAPTURE,
+        ]
+    } else {
+        [
+            MoveFlags::QUEEN_PROM,
+            MoveFlags::ROOK_PROM,
+            MoveFlags::BISHOP_PROM,
+            MoveFlags::KNIGHT_PROM,
+        ]
+    };
+    for flag in flags {
+        moves.push(Move::new(from_sq.into(), to_sq.into(), flag));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{attacks::init_tables, fen::san_to_int};
+
+    use super::*;
+
+    /// Returns the target squares of all generated moves starting on the given square
+    fn targets_from(position: &Position, from: &str) -> Vec<u8> {
+        let from = san_to_int(from).unwrap();
+        let mut targets: Vec<u8> = position
+            .generate_moves()
+            .iter()
+            .filter(|m| m.get_from() == from)
+            .map(|m| m.get_to())
+            .collect();
+        targets.sort();
+        targets
+    }
+
+    fn squares(sans: &[&str]) -> Vec<u8> {
+        let mut squares: Vec<u8> = sans.iter().map(|san| san_to_int(san).unwrap()).collect();
+        squares.sort();
+        squares
+    }
+
+    #[test]
+    fn generate_start_position_moves_correctly() {
+        init_tables();
+        let position = Position::from("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+        assert!(position.generate_moves().len() == 20);
+    }
+
+    #[test]
+    fn restrict_pinned_pieces_to_pin_ray() {
+        init_tables();
+        let position = Position::from("4k3/8/8/8/4r3/8/4R3/4K3 w - - 0 1");
+        assert!(targets_from(&position, "e2") == squares(&["e3", "e4"]));
+
+        let position = Position::from("4k3/8/8/8/4r3/8/4B3/4K3 w - - 0 1");
+        assert!(targets_from(&position, "e2").is_empty());
+
+        let position = Position::from("4k3/8/8/1b6/8/8/4P3/5K2 w - - 0 1");
+        assert!(targets_from(&position, "e2").is_empty());
+    }
+
+    #[test]
+    fn evade_single_check_by_block_or_capture() {
+        init_tables();
+        let position = Position::from("k3r3/8/8/8/8/2N5/8/R3K3 w - - 0 1");
+        assert!(targets_from(&position, "c3") == squares(&["e2", "e4"]));
+        assert!(targets_from(&position, "a1").is_empty());
+
+        let position = Position::from("k4R2/8/8/8/8/5n2/8/4K3 w - - 0 1");
+        assert!(targets_from(&position, "f8") == squares(&["f3"]));
+    }
+
+    #[test]
+    fn only_move_king_in_double_check() {
+        init_tables();
+        let position = Position::from("k7/8/8/8/8/5n2/8/R3K2r w - - 0 1");
+        assert!(position.in_check());
+        assert!(targets_from(&position, "a1").is_empty());
+        assert!(targets_from(&position, "e1") == squares(&["e2", "f2"]));
+    }
+
+    #[test]
+    fn keep_king_off_attacked_squares() {
+        init_tables();
+        let position = Position::from("4k3/8/8/8/8/8/8/r3K3 w - - 0 1");
+        assert!(targets_from(&position, "e1") == squares(&["d2", "e2", "f2"]));
+
+        let position = Position::from("4k3/8/8/8/8/8/3q4/4K3 w - - 0 1");
+        assert!(targets_from(&position, "e1") == squares(&["d2", "f1"]));
+    }
+
+    #[test]
+    fn generate_en_passant_captures_correctly() {
+        init_tables();
+        let position = Position::from("4k3/8/8/2PpP3/8/8/8/4K3 w - d6 0 1");
+        let ep_captures: Vec<Move> = position
+            .generate_moves()
+            .iter()
+            .copied()
+            .filter(|m| m.get_flag() == MoveFlags::EP_CAPTURE.bits() as u8)
+            .collect();
+        assert!(ep_captures.len() == 2);
+        assert!(ep_captures
+            .iter()
+            .all(|m| m.get_to() == san_to_int("d6").unwrap()));
+    }
+
+    #[test]
+    fn evade_check_with_en_passant_capture() {
+        init_tables();
+        let position = Position::from("8/8/8/2k5/3Pp3/8/8/4K3 b - d3 0 1");
+        assert!(position.in_check());
+        assert!(targets_from(&position, "e4") == squares(&["d3"]));
+    }
+
+    #[test]
+    fn refuse_en_passant_exposing_king_along_rank() {
+        init_tables();
+        let position = Position::from("8/8/8/K1pP3r/8/8/8/4k3 w - c6 0 1");
+        assert!(targets_from(&position, "d5") == squares(&["d6"]));
+
+        let position = Position::from("8/8/8/8/k2Pp2Q/8/8/4K3 b - d3 0 1");
+        assert!(targets_from(&position, "e4") == squares(&["e3"]));
+    }
+
+    #[test]
+    fn generate_castling_moves_correctly() {
+        init_tables();
+        let position = Position::from("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+        assert!(
+            targets_from(&position, "e1") == squares(&["c1", "d1", "d2", "e2", "f2", "f1", "g1"])
+        );
+
+        let position = Position::from("r3k2r/8/8/8/8/8/8/R3K2R b Kq - 0 1");
+        assert!(targets_from(&position, "e8") == squares(&["c8", "d8", "d7", "e7", "f7", "f8"]));
+    }
+
+    #[test]
+    fn refuse_castling_through_blockers_and_attacks() {
+        init_tables();
+        let position = Position::from("r3kr2/8/8/8/8/8/8/R3K2R w KQ - 0 1");
+        assert!(targets_from(&position, "e1") == squares(&["c1", "d1", "d2", "e2"]));
+
+        let position = Position::from("r3k2r/8/8/8/8/8/8/RN2K2R w KQ - 0 1");
+        assert!(targets_from(&position, "e1") == squares(&["d1", "d2", "e2", "f2", "f1", "g1"]));
+
+        let position = Position::from("r3k2r/8/8/8/8/8/8/R3K1qR w KQ - 0 1");
+        assert!(targets_from(&position, "e1") == squares(&["d2", "e2"]));
+    }
+}
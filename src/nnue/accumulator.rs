@@ -0,0 +1,190 @@
+use std::sync::Arc;
+
+use super::{feature_index, Network, HIDDEN};
+use crate::{piece::Piece, util::Color};
+
+/// Hidden layer values of one perspective before activation, aligned for vector loads
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C, align(64))]
+pub struct Accumulator(pub [i16; HIDDEN]);
+
+impl Default for Accumulator {
+    fn default() -> Self {
+        Accumulator([0; HIDDEN])
+    }
+}
+
+// Updates wrap around instead of overflowing. Values which fit into i16 come out right in any
+// order of updates, networks with larger sums give meaningless but harmless outputs.
+impl Accumulator {
+    fn add(&mut self, weights: &[i16; HIDDEN]) {
+        for (value, weight) in self.0.iter_mut().zip(weights) {
+            *value = value.wrapping_add(*weight);
+        }
+    }
+
+    fn sub(&mut self, weights: &[i16; HIDDEN]) {
+        for (value, weight) in self.0.iter_mut().zip(weights) {
+            *value = value.wrapping_sub(*weight);
+        }
+    }
+
+    fn sub_add(&mut self, sub: &[i16; HIDDEN], add: &[i16; HIDDEN]) {
+        for ((value, sub), add) in self.0.iter_mut().zip(sub).zip(add) {
+            *value = value.wrapping_add(add.wrapping_sub(*sub));
+        }
+    }
+}
+
+/// Accumulators of both perspectives for every position on the path from the root, kept by a
+/// `Position` while evaluating with a network
+#[derive(Clone)]
+pub struct NnueState {
+    network: Arc<Network>,
+    /// Pairs of white and black accumulators, the last one belongs to the current position
+    stack: Vec<[Accumulator; 2]>,
+}
+
+impl NnueState {
+    /// Computes the accumulators of the given pieces from scratch
+    pub fn new(network: Arc<Network>, pieces: impl Iterator<Item = (Piece, u8)>) -> Self {
+        let mut accumulators = [network.feature_biases; 2];
+        for (piece, square) in pieces {
+            for color in [Color::White, Color::Black] {
+                accumulators[color as usize]
+                    .add(&network.feature_weights[feature_index(piece, square, color)]);
+            }
+        }
+        NnueState {
+            network,
+            stack: vec![accumulators],
+        }
+    }
+
+    /// Returns the accumulator of a perspective in the current position
+    pub fn accumulator(&self, perspective: Color) -> &Accumulator {
+        &self.current()[perspective as usize]
+    }
+
+    /// Saves the accumulators before a move is made
+    pub fn push(&mut self) {
+        let accumulators = *self.current();
+        self.stack.push(accumulators);
+    }
+
+    /// Restores the accumulators from before the last move
+    pub fn pop(&mut self) {
+        debug_assert!(self.stack.len() > 1, "no accumulators to restore");
+        self.stack.pop();
+    }
+
+    pub fn add_piece(&mut self, piece: Piece, square: u8) {
+        let accumulators = self
+            .stack
+            .last_mut()
+            .expect("accumulator stack is never empty");
+        for color in [Color::White, Color::Black] {
+            accumulators[color as usize]
+                .add(&self.network.feature_weights[feature_index(piece, square, color)]);
+        }
+    }
+
+    pub fn remove_piece(&mut self, piece: Piece, square: u8) {
+        let accumulators = self
+            .stack
+            .last_mut()
+            .expect("accumulator stack is never empty");
+        for color in [Color::White, Color::Black] {
+            accumulators[color as usize]
+                .sub(&self.network.feature_weights[feature_index(piece, square, color)]);
+        }
+    }
+
+    pub fn move_piece(&mut self, piece: Piece, from_sq: u8, to_sq: u8) {
+        let accumulators = self
+            .stack
+            .last_mut()
+            .expect("accumulator stack is never empty");
+        for color in [Color::White, Color::Black] {
+            accumulators[color as usize].sub_add(
+                &self.network.feature_weights[feature_index(piece, from_sq, color)],
+                &self.network.feature_weights[feature_index(piece, to_sq, color)],
+            );
+        }
+    }
+
+    /// Evaluates the current position in centipawns from the point of view of the side to move
+    pub fn evaluate(&self, side_to_move: Color) -> i32 {
+        let [white, black] = self.current();
+        match side_to_move {
+            Color::White => self.network.evaluate(white, black),
+            Color::Black => self.network.evaluate(black, white),
+        }
+    }
+
+    fn current(&self) -> &[Accumulator; 2] {
+        self.stack.last().expect("accumulator stack is never empty")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{attacks::init_tables, nnue::tests::random_network, position::Position};
+
+    use super::*;
+
+    #[test]
+    fn survive_networks_with_huge_weights() {
+        init_tables();
+        let mut network = Network::zeroed();
+        for row in network.feature_weights.iter_mut() {
+            *row = [i16::MAX; HIDDEN];
+        }
+        network.output_weights = [i16::MAX; 2 * HIDDEN];
+        let mut position = Position::from(crate::uci::START_FEN);
+        position.set_network(Some(Arc::new(network)));
+
+        for &mv in position.clone().generate_moves().iter() {
+            position.make_move(mv);
+            position.nnue().unwrap().evaluate(position.side_to_move());
+            position.unmake_move();
+        }
+    }
+
+    #[test]
+    fn update_accumulators_incrementally() {
+        init_tables();
+        let network = Arc::new(random_network(3));
+        // Castling, en passant and promotions with and without captures
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r2q1rk1/pP1p2pp/Q4n2/bbp1p3/Np6/1B3NBn/pPPP1PPP/R3K2R b KQ - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        ] {
+            let mut position = Position::from(fen);
+            position.set_network(Some(Arc::clone(&network)));
+            let root = position.nnue().unwrap().clone();
+
+            for &mv in position.clone().generate_moves().iter() {
+                position.make_move(mv);
+                for &reply in position.clone().generate_moves().iter() {
+                    position.make_move(reply);
+                    let mut refreshed = position.clone();
+                    refreshed.set_network(Some(Arc::clone(&network)));
+                    for color in [Color::White, Color::Black] {
+                        assert!(
+                            position.nnue().unwrap().accumulator(color)
+                                == refreshed.nnue().unwrap().accumulator(color)
+                        );
+                    }
+                    position.unmake_move();
+                }
+                position.unmake_move();
+            }
+
+            for color in [Color::White, Color::Black] {
+                assert!(position.nnue().unwrap().accumulator(color) == root.accumulator(color));
+            }
+        }
+    }
+}
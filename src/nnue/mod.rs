@@ -0,0 +1,262 @@
+//! Efficiently updatable neural network evaluation.
+//!
+//! The network is a perspective network with 768 inputs, one per piece type, color and square,
+//! a hidden layer of `HIDDEN` neurons computed once for each side and a single output. The
+//! hidden layer is kept in accumulators which are updated incrementally as pieces move, see
+//! [`NnueState`].
+//!
+//! # File format
+//!
+//! All numbers are little endian.
+//!
+//! | Size             | Content                                                        |
+//! |------------------|----------------------------------------------------------------|
+//! | 4 bytes          | Magic `LNUE`                                                   |
+//! | u32              | Format version, currently 1                                    |
+//! | u32              | Number of inputs, 768                                          |
+//! | u32              | Number of hidden neurons, must equal `HIDDEN`                  |
+//! | 768 x HIDDEN i16 | Input weights, one row of `HIDDEN` weights per input feature   |
+//! | HIDDEN i16       | Hidden biases                                                  |
+//! | 2 x HIDDEN i16   | Output weights for the side to move followed by the other side |
+//! | i32              | Output bias                                                    |
+//!
+//! Input and hidden weights are quantized by `QA`, output weights by `QB` and the output bias by
+//! `QA * QB`. The input feature of a piece seen from a perspective is
+//! `(own ? 0 : 384) + piece_type * 64 + square`, where squares are mirrored vertically for black.
+
+mod accumulator;
+mod simd;
+
+use std::{
+    fs::File,
+    io::{self, BufReader, Read, Write},
+    path::Path,
+};
+
+pub use accumulator::{Accumulator, NnueState};
+
+use crate::{piece::Piece, search::MATE_BOUND, util::Color};
+
+/// Number of input features
+pub const INPUTS: usize = 768;
+/// Number of hidden neurons per perspective
+pub const HIDDEN: usize = 256;
+/// Quantization of the input and hidden layer, activations are clipped to `0..=QA`
+pub const QA: i32 = 255;
+/// Quantization of the output weights
+pub const QB: i32 = 64;
+/// Conversion from the network output to centipawns
+pub const SCALE: i32 = 400;
+
+const MAGIC: &[u8; 4] = b"LNUE";
+const VERSION: u32 = 1;
+
+/// Quantized network weights
+#[derive(Clone)]
+pub struct Network {
+    /// `INPUTS` rows of `HIDDEN` weights so that a feature update touches contiguous memory
+    pub feature_weights: Box<[[i16; HIDDEN]; INPUTS]>,
+    pub feature_biases: Accumulator,
+    /// Weights of the side to move's hidden layer followed by the other side's
+    pub output_weights: [i16; 2 * HIDDEN],
+    pub output_bias: i32,
+}
+
+impl Network {
+    /// Creates a network with all weights set to zero
+    pub fn zeroed() -> Self {
+        let feature_weights = vec![[0; HIDDEN]; INPUTS]
+            .into_boxed_slice()
+            .try_into()
+            .expect("feature weights have INPUTS rows");
+        Network {
+            feature_weights,
+            feature_biases: Accumulator::default(),
+            output_weights: [0; 2 * HIDDEN],
+            output_bias: 0,
+        }
+    }
+
+    /// Loads a network from a file in the format described in the module documentation
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::read(BufReader::new(File::open(path)?))
+    }
+
+    /// Reads a network in the format described in the module documentation
+    pub fn read<R: Read>(mut reader: R) -> io::Result<Self> {
+        let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid("not a network file"));
+        }
+        if read_u32(&mut reader)? != VERSION {
+            return Err(invalid("unsupported network version"));
+        }
+        if read_u32(&mut reader)? as usize != INPUTS || read_u32(&mut reader)? as usize != HIDDEN {
+            return Err(invalid("network architecture does not match"));
+        }
+
+        let mut network = Network::zeroed();
+        for row in network.feature_weights.iter_mut() {
+            read_i16s(&mut reader, row)?;
+        }
+        read_i16s(&mut reader, &mut network.feature_biases.0)?;
+        read_i16s(&mut reader, &mut network.output_weights)?;
+        let mut bias = [0; 4];
+        reader.read_exact(&mut bias)?;
+        network.output_bias = i32::from_le_bytes(bias);
+
+        // Trailing data means the file was written for a different layout
+        if reader.read(&mut [0])? != 0 {
+            return Err(invalid("unexpected data after the network"));
+        }
+        Ok(network)
+    }
+
+    /// Writes the network in the format described in the module documentation
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(MAGIC)?;
+        for value in [VERSION, INPUTS as u32, HIDDEN as u32] {
+            writer.write_all(&value.to_le_bytes())?;
+        }
+        for row in self.feature_weights.iter() {
+            write_i16s(&mut writer, row)?;
+        }
+        write_i16s(&mut writer, &self.feature_biases.0)?;
+        write_i16s(&mut writer, &self.output_weights)?;
+        writer.write_all(&self.output_bias.to_le_bytes())
+    }
+
+    /// Computes the output in centipawns from the accumulators of the side to move and the other
+    /// side. The output is kept below the mate scores whatever the weights are.
+    pub fn evaluate(&self, us: &Accumulator, them: &Accumulator) -> i32 {
+        let (own_weights, other_weights) = self.output_weights.split_at(HIDDEN);
+        let sum = simd::clipped_dot(&us.0, own_weights) as i64
+            + simd::clipped_dot(&them.0, other_weights) as i64;
+        let output = (sum + self.output_bias as i64) * SCALE as i64 / (QA * QB) as i64;
+        output.clamp(-(MATE_BOUND - 1) as i64, (MATE_BOUND - 1) as i64) as i32
+    }
+}
+
+/// Returns the input feature of a piece on a square seen from the given perspective
+pub fn feature_index(piece: Piece, square: u8, perspective: Color) -> usize {
+    let (side, square) = match perspective {
+        Color::White => (piece.get_color() != Color::White, square),
+        Color::Black => (piece.get_color() != Color::Black, square ^ 56),
+    };
+    side as usize * 384 + piece.get_piece_type().bits() as usize * 64 + square as usize
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_i16s<R: Read>(reader: &mut R, values: &mut [i16]) -> io::Result<()> {
+    let mut bytes = vec![0; values.len() * 2];
+    reader.read_exact(&mut bytes)?;
+    for (value, bytes) in values.iter_mut().zip(bytes.chunks_exact(2)) {
+        *value = i16::from_le_bytes([bytes[0], bytes[1]]);
+    }
+    Ok(())
+}
+
+fn write_i16s<W: Write>(writer: &mut W, values: &[i16]) -> io::Result<()> {
+    let bytes: Vec<u8> = values
+        .iter()
+        .flat_map(|value| value.to_le_bytes())
+        .collect();
+    writer.write_all(&bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use crate::{attacks::init_tables, piece::PieceTypes, position::Position};
+
+    use super::*;
+
+    /// Builds a network with small random weights
+    pub fn random_network(seed: u64) -> Network {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut network = Network::zeroed();
+        for row in network.feature_weights.iter_mut() {
+            row.iter_mut().for_each(|w| *w = rng.gen_range(-64..64));
+        }
+        network
+            .feature_biases
+            .0
+            .iter_mut()
+            .for_each(|b| *b = rng.gen_range(0..128));
+        network
+            .output_weights
+            .iter_mut()
+            .for_each(|w| *w = rng.gen_range(-64..64));
+        network.output_bias = rng.gen_range(-1000..1000);
+        network
+    }
+
+    #[test]
+    fn read_and_write_networks() {
+        let network = random_network(1);
+        let mut bytes = Vec::new();
+        network.write(&mut bytes).unwrap();
+        assert!(bytes.len() == 16 + 2 * (INPUTS * HIDDEN + 3 * HIDDEN) + 4);
+
+        let loaded = Network::read(bytes.as_slice()).unwrap();
+        assert!(loaded.feature_weights == network.feature_weights);
+        assert!(loaded.feature_biases.0 == network.feature_biases.0);
+        assert!(loaded.output_weights == network.output_weights);
+        assert!(loaded.output_bias == network.output_bias);
+
+        assert!(Network::read(&bytes[..bytes.len() - 1]).is_err());
+        let mut extended = bytes.clone();
+        extended.push(0);
+        assert!(Network::read(extended.as_slice()).is_err());
+        bytes[0] = b'X';
+        assert!(Network::read(bytes.as_slice()).is_err());
+    }
+
+    #[test]
+    fn keep_extreme_outputs_below_mate_scores() {
+        let mut network = Network::zeroed();
+        network.feature_biases.0 = [i16::MAX; HIDDEN];
+        network.output_weights = [i16::MAX; 2 * HIDDEN];
+        network.output_bias = i32::MAX;
+        let accumulator = network.feature_biases;
+        assert!(network.evaluate(&accumulator, &accumulator) == MATE_BOUND - 1);
+
+        network.output_weights = [i16::MIN; 2 * HIDDEN];
+        network.output_bias = i32::MIN;
+        assert!(network.evaluate(&accumulator, &accumulator) == -(MATE_BOUND - 1));
+    }
+
+    #[test]
+    fn mirror_features_between_perspectives() {
+        let white_pawn = Piece::new(Color::White, PieceTypes::PAWN);
+        let black_pawn = Piece::new(Color::Black, PieceTypes::PAWN);
+        // e2 for white looks like e7 for black
+        assert!(feature_index(white_pawn, 12, Color::White) == 12);
+        assert!(feature_index(black_pawn, 52, Color::Black) == 12);
+        assert!(feature_index(white_pawn, 12, Color::Black) == 384 + 52);
+        let king = Piece::new(Color::Black, PieceTypes::KING);
+        assert!(feature_index(king, 60, Color::White) == 384 + 5 * 64 + 60);
+    }
+
+    #[test]
+    fn evaluate_mirrored_positions_equally() {
+        init_tables();
+        let network = std::sync::Arc::new(random_network(2));
+        let mut position = Position::from("4k3/8/8/3p4/4P3/8/8/R3K3 w - - 0 1");
+        let mut mirrored = Position::from("r3k3/8/8/4p3/3P4/8/8/4K3 b - - 0 1");
+        position.set_network(Some(network.clone()));
+        mirrored.set_network(Some(network));
+
+        let score = position.nnue().unwrap().evaluate(Color::White);
+        assert!(score == mirrored.nnue().unwrap().evaluate(Color::Black));
+    }
+}
@@ -0,0 +1,78 @@
+//! Output layer kernels. The AVX2 version is picked at runtime when the CPU supports it, any
+//! other target uses the scalar version which the compiler may still vectorize.
+
+use super::{HIDDEN, QA};
+
+/// Computes the dot product of the clipped accumulator values and the output weights
+pub fn clipped_dot(values: &[i16; HIDDEN], weights: &[i16]) -> i32 {
+    debug_assert!(weights.len() == HIDDEN);
+    #[cfg(target_arch = "x86_64")]
+    {
+        if is_x86_feature_detected!("avx2") {
+            // SAFETY: the CPU supports AVX2 and both slices hold `HIDDEN` values
+            return unsafe { avx2::clipped_dot(values, weights) };
+        }
+    }
+    scalar_clipped_dot(values, weights)
+}
+
+pub fn scalar_clipped_dot(values: &[i16; HIDDEN], weights: &[i16]) -> i32 {
+    values
+        .iter()
+        .zip(weights)
+        .map(|(&value, &weight)| value.clamp(0, QA as i16) as i32 * weight as i32)
+        .sum()
+}
+
+#[cfg(target_arch = "x86_64")]
+mod avx2 {
+    use std::arch::x86_64::*;
+
+    use super::{HIDDEN, QA};
+
+    /// Number of i16 lanes in a 256 bit register
+    const LANES: usize = 16;
+
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn clipped_dot(values: &[i16; HIDDEN], weights: &[i16]) -> i32 {
+        let zero = _mm256_setzero_si256();
+        let max = _mm256_set1_epi16(QA as i16);
+        let mut sum = _mm256_setzero_si256();
+
+        for idx in (0..HIDDEN).step_by(LANES) {
+            let value = _mm256_loadu_si256(values.as_ptr().add(idx) as *const __m256i);
+            let weight = _mm256_loadu_si256(weights.as_ptr().add(idx) as *const __m256i);
+            let clipped = _mm256_min_epi16(_mm256_max_epi16(value, zero), max);
+            // Multiplies the i16 lanes and adds adjacent products into i32 lanes
+            sum = _mm256_add_epi32(sum, _mm256_madd_epi16(clipped, weight));
+        }
+
+        let sum = _mm_add_epi32(
+            _mm256_castsi256_si128(sum),
+            _mm256_extracti128_si256::<1>(sum),
+        );
+        let sum = _mm_add_epi32(sum, _mm_shuffle_epi32::<0b01_00_11_10>(sum));
+        let sum = _mm_add_epi32(sum, _mm_shuffle_epi32::<0b10_11_00_01>(sum));
+        _mm_cvtsi128_si32(sum)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use super::*;
+
+    #[test]
+    fn match_scalar_dot_product() {
+        let mut rng = StdRng::seed_from_u64(4);
+        for _ in 0..100 {
+            let mut values = [0; HIDDEN];
+            values
+                .iter_mut()
+                .for_each(|v| *v = rng.gen_range(-500..500));
+            let weights: Vec<i16> = (0..HIDDEN).map(|_| rng.gen_range(-128..128)).collect();
+            assert!(clipped_dot(&values, &weights) == scalar_clipped_dot(&values, &weights));
+        }
+    }
+}
@@ -0,0 +1,151 @@
+use crate::{cmove::Move, position::Position};
+
+impl Position {
+    /// Counts the leaf nodes of the legal move tree up to the given depth
+    pub fn perft(&mut self, depth: usize) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+
+        let moves = self.generate_moves();
+
+        // Bulk counting: the number of legal moves is the number of leaves one ply ahead
+        if depth == 1 {
+            return moves.len() as u64;
+        }
+
+        let mut nodes = 0;
+        for &mv in moves.iter() {
+            self.make_move(mv);
+            nodes += self.perft(depth - 1);
+            self.unmake_move();
+        }
+        nodes
+    }
+
+    /// Splits the perft count of the given depth by root move
+    pub fn divide(&mut self, depth: usize) -> Vec<(Move, u64)> {
+        let mut counts = Vec::new();
+        if depth == 0 {
+            return counts;
+        }
+
+        for &mv in self.generate_moves().iter() {
+            self.make_move(mv);
+            counts.push((mv, self.perft(depth - 1)));
+            self.unmake_move();
+        }
+        counts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::attacks::init_tables;
+
+    use super::*;
+
+    /// Reference positions with their known node counts by depth, starting at depth 1
+    const PERFT_SUITE: [(&str, &[u64]); 6] = [
+        (
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            &[20, 400, 8902, 197281, 4865609, 119060324],
+        ),
+        (
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            &[48, 2039, 97862, 4085603, 193690690],
+        ),
+        (
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            &[14, 191, 2812, 43238, 674624, 11030083],
+        ),
+        (
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            &[6, 264, 9467, 422333, 15833292],
+        ),
+        (
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+            &[44, 1486, 62379, 2103487, 89941194],
+        ),
+        (
+            "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+            &[46, 2079, 89890, 3894594, 164075551],
+        ),
+    ];
+
+    /// Runs every suite position up to the deepest depth whose count does not exceed the limit
+    fn run_suite(max_nodes: u64) {
+        init_tables();
+        for (fen, counts) in PERFT_SUITE {
+            let mut position = Position::from(fen);
+            for (idx, expected) in counts.iter().enumerate() {
+                if *expected > max_nodes {
+                    break;
+                }
+                assert_eq!(
+                    position.perft(idx + 1),
+                    *expected,
+                    "{} depth {}",
+                    fen,
+                    idx + 1
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn count_perft_nodes_correctly() {
+        run_suite(100_000);
+    }
+
+    #[test]
+    #[ignore = "deep perft, run with --release -- --ignored"]
+    fn count_deep_perft_nodes_correctly() {
+        run_suite(u64::MAX);
+    }
+
+    #[test]
+    fn count_edge_case_positions_correctly() {
+        init_tables();
+        for (fen, depth, expected) in [
+            // Illegal en passant captures
+            ("3k4/3p4/8/K1P4r/8/8/8/8 b - - 0 1", 6, 1134888),
+            ("8/8/4k3/8/2p5/8/B2P2K1/8 w - - 0 1", 6, 1015133),
+            // En passant capture giving check
+            ("8/8/1k6/2b5/2pP4/8/5K2/8 b - d3 0 1", 6, 1440467),
+            // Castling giving check
+            ("5k2/8/8/8/8/8/8/4K2R w K - 0 1", 6, 661072),
+            ("3k4/8/8/8/8/8/8/R3K3 w Q - 0 1", 6, 803711),
+            // Castling rights lost and castling prevented
+            ("r3k2r/1b4bq/8/8/8/8/7B/R3K2R w KQkq - 0 1", 4, 1274206),
+            ("r3k2r/8/3Q4/8/8/5q2/8/R3K2R b KQkq - 0 1", 4, 1720476),
+            // Promotions out of check and giving check
+            ("2K2r2/4P3/8/8/8/8/8/3k4 w - - 0 1", 6, 3821001),
+            ("4k3/1P6/8/8/8/8/K7/8 w - - 0 1", 6, 217342),
+            ("8/P1k5/K7/8/8/8/8/8 w - - 0 1", 6, 92683),
+            // Discovered check
+            ("8/8/1P2K3/8/2n5/1q6/8/5k2 b - - 0 1", 5, 1004658),
+            // Stalemate and checkmate
+            ("K1k5/8/P7/8/8/8/8/8 w - - 0 1", 6, 2217),
+            ("8/k1P5/8/1K6/8/8/8/8 w - - 0 1", 7, 567584),
+            ("8/8/2k5/5q2/5n2/8/5K2/8 b - - 0 1", 4, 23527),
+        ] {
+            let mut position = Position::from(fen);
+            assert_eq!(position.perft(depth), expected, "{}", fen);
+        }
+    }
+
+    #[test]
+    fn divide_perft_by_root_move_correctly() {
+        init_tables();
+        let mut position =
+            Position::from("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+        let counts = position.divide(3);
+
+        assert!(counts.len() == 20);
+        assert!(counts.iter().map(|(_, nodes)| nodes).sum::<u64>() == 8902);
+        assert!(counts
+            .iter()
+            .any(|(mv, nodes)| mv.to_string() == "e2e4" && *nodes == 600));
+    }
+}
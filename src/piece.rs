@@ -2,6 +2,7 @@ use crate::util::Color;
 use bitflags::bitflags;
 
 bitflags! {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
     pub struct PieceTypes: u8 {
         const PAWN          = 0b000;
         const KNIGHT        = 0b001;
@@ -12,7 +13,7 @@ bitflags! {
     }
 }
 
-#[derive(PartialEq, Copy, Clone)]
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
 pub struct Piece(u8);
 
 impl Piece {
@@ -52,6 +53,16 @@ impl Piece {
         }
     }
 
+    /// Returns the type of the piece
+    pub fn get_piece_type(&self) -> PieceTypes {
+        PieceTypes::from_bits_retain(self.0 & 0b111)
+    }
+
+    /// Returns the char representation of the piece
+    pub fn to_char(&self) -> char {
+        Piece::PIECE_CHARS[self.get_index()]
+    }
+
     /// Returns the index of the piece for access in array structures
     pub fn get_index(&self) -> usize {
         let piece_type = self.0 & 0b111;
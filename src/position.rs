@@ -1,16 +1,40 @@
+use std::sync::Arc;
+
 use crate::{
     attacks::{
-        lookup_bishop_att, lookup_knight_att, lookup_pawn_att, lookup_queen_att, lookup_rook_att,
+        lookup_between, lookup_bishop_att, lookup_king_att, lookup_knight_att, lookup_pawn_att,
+        lookup_rook_att,
     },
+    castling_rights::CastlingRights,
     cmove::{Move, MoveFlags},
-    fen::{parse_fen, BoardState},
+    fen::{format_fen, parse_fen, BoardState, EnPassantSquare, HalfMoveClock},
+    nnue::{Network, NnueState},
     piece::{Piece, PieceTypes},
-    util::{bb_from_square, enumerate_bits, mailbox_to_bb, opp, relative_rank, Bitboard, Color},
+    util::{bb_from_square, enumerate_bits, mailbox_to_bb, opp, Bitboard, Color},
+    zobrist::{castling_key, en_passant_key, piece_key, ZOBRIST},
 };
 
+/// Irreversible state needed to take back a move
+#[derive(Clone)]
+struct Undo {
+    mv: Move,
+    captured: Option<Piece>,
+    castling_rights: CastlingRights,
+    en_passant_square: EnPassantSquare,
+    halfmove_clock: HalfMoveClock,
+    hash: u64,
+    pawn_hash: u64,
+}
+
+#[derive(Clone)]
 pub struct Position {
     board_state: BoardState,
     pub pieces: [Bitboard; 12],
+    history: Vec<Undo>,
+    hash: u64,
+    pawn_hash: u64,
+    /// Network accumulators, updated with the pieces while a network is used for evaluation
+    nnue: Option<Box<NnueState>>,
 }
 
 impl From<&str> for Position {
@@ -23,175 +47,421 @@ impl From<&str> for Position {
             pieces[idx] = bb;
         }
 
-        Self {
+        let mut position = Self {
             pieces,
             board_state: parsed_fen,
-        }
+            history: Vec::new(),
+            hash: 0,
+            pawn_hash: 0,
+            nnue: None,
+        };
+        position.hash = position.compute_hash();
+        position.pawn_hash = position.compute_pawn_hash();
+        position
     }
 }
 
 impl Position {
-    /// Generates all legal moves in the position
-    pub fn generate_moves(&self) {
-        let ally_color = self.board_state.1;
-        let opp_color = opp(ally_color);
-
-        let ally_pieces_bb = self.all_pieces_bb(ally_color.into());
-        let opp_pieces_bb = self.all_pieces_bb(opp_color.into());
-        let all_pieces_bb = ally_pieces_bb | opp_pieces_bb;
-        let mut moves: Vec<Move> = Vec::new();
-
-        let knight = Piece::new(ally_color, PieceTypes::KNIGHT);
-
-        // Knight moves
-        // TODO: Illegal moves
-        enumerate_bits(self.pieces[knight.get_index()], |from_sq| {
-            let att = lookup_knight_att(from_sq) & !all_pieces_bb;
-            let captures = att & opp_pieces_bb;
-            let quiet = att & !opp_pieces_bb;
-
-            enumerate_bits(captures, |to_sq| {
-                moves.push(Move::new(from_sq.into(), to_sq.into(), MoveFlags::CAPTURE));
-            });
-            enumerate_bits(quiet, |to_sq| {
-                moves.push(Move::new(from_sq.into(), to_sq.into(), MoveFlags::QUIET));
-            });
-        });
+    /// Applies a legal move to the position
+    pub fn make_move(&mut self, mv: Move) {
+        let color = self.board_state.1;
+        let from_sq = mv.get_from();
+        let to_sq = mv.get_to();
+        let flags = mv.get_flags();
+        let piece = self.board_state.0[from_sq as usize].expect("no piece on from square");
 
-        // Pawn moves
-        // TODO: illegal moves
-        let pawn = Piece::new(ally_color, PieceTypes::PAWN);
-        let pawn_direction: i8 = if ally_color == Color::White { 1 } else { -1 };
-        let prom_rank = relative_rank(7, ally_color);
-
-        enumerate_bits(self.pieces[pawn.get_index()], |from_sq| {
-            let to_sq = (from_sq as i8 + 8 * pawn_direction) as u8;
-            let to_bb = bb_from_square(to_sq);
-            let from_bb = bb_from_square(from_sq);
-
-            if to_bb & !all_pieces_bb != 0 {
-                // Pawn promotions
-                if to_bb & prom_rank != 0 {
-                    moves.push(Move::new(
-                        from_sq.into(),
-                        to_sq.into(),
-                        MoveFlags::KNIGHT_PROM,
-                    ));
-                    moves.push(Move::new(
-                        from_sq.into(),
-                        to_sq.into(),
-                        MoveFlags::BISHOP_PROM,
-                    ));
-                    moves.push(Move::new(
-                        from_sq.into(),
-                        to_sq.into(),
-                        MoveFlags::ROOK_PROM,
-                    ));
-                    moves.push(Move::new(
-                        from_sq.into(),
-                        to_sq.into(),
-                        MoveFlags::QUEEN_PROM,
-                    ));
-                }
-                // Pawn single push
-                else {
-                    moves.push(Move::new(from_sq.into(), to_sq.into(), MoveFlags::QUIET));
-                }
-
-                // Pawn double push
-                let to_sq = (to_sq as i8 + 8 * pawn_direction) as u8;
-                if bb_from_square(to_sq) & !all_pieces_bb != 0
-                    && from_bb & relative_rank(1, ally_color) != 0
-                {
-                    moves.push(Move::new(
-                        from_sq.into(),
-                        to_sq.into(),
-                        MoveFlags::DOUBLE_PAWN_PUSH,
-                    ));
-                }
+        let captured_sq = if flags == MoveFlags::EP_CAPTURE {
+            if color == Color::White {
+                to_sq - 8
+            } else {
+                to_sq + 8
             }
+        } else {
+            to_sq
+        };
+        let captured = if mv.is_capture() {
+            self.board_state.0[captured_sq as usize]
+        } else {
+            None
+        };
 
-            let att = lookup_pawn_att(from_sq, ally_color) & opp_pieces_bb;
-            let captures = att & !prom_rank;
-            let prom_captures = att & prom_rank;
-
-            // Capture pawn moves
-            enumerate_bits(captures, |to_sq| {
-                moves.push(Move::new(from_sq.into(), to_sq.into(), MoveFlags::CAPTURE))
-            });
-
-            // Promotion capture pawn moves
-            enumerate_bits(prom_captures, |to_sq| {
-                moves.push(Move::new(
-                    from_sq.into(),
-                    to_sq.into(),
-                    MoveFlags::BISHOP_PROM_CAPTURE,
-                ));
-                moves.push(Move::new(
-                    from_sq.into(),
-                    to_sq.into(),
-                    MoveFlags::ROOK_PROM_CAPTURE,
-                ));
-                moves.push(Move::new(
-                    from_sq.into(),
-                    to_sq.into(),
-                    MoveFlags::QUEEN_PROM_CAPTURE,
-                ));
-                moves.push(Move::new(
-                    from_sq.into(),
-                    to_sq.into(),
-                    MoveFlags::KNIGHT_PROM_CAPTURE,
-                ));
-            });
+        self.history.push(Undo {
+            mv,
+            captured,
+            castling_rights: self.board_state.2,
+            en_passant_square: self.board_state.3,
+            halfmove_clock: self.board_state.4,
+            hash: self.hash,
+            pawn_hash: self.pawn_hash,
         });
 
-        let queen = Piece::new(ally_color, PieceTypes::QUEEN);
+        if let Some(nnue) = &mut self.nnue {
+            nnue.push();
+        }
+
+        // Castling and en passant keys are swapped out as a whole once the pieces are moved
+        self.hash ^= castling_key(self.board_state.2) ^ self.en_passant_hash();
+
+        if captured.is_some() {
+            self.remove_piece(captured_sq);
+        }
+        self.move_piece(from_sq, to_sq);
+
+        if let Some(piece_type) = mv.get_promotion_piece_type() {
+            self.remove_piece(to_sq);
+            self.put_piece(Piece::new(color, piece_type), to_sq);
+        }
+
+        // The rook hops over the king
+        if flags == MoveFlags::KING_CASTLE {
+            self.move_piece(to_sq + 1, to_sq - 1);
+        } else if flags == MoveFlags::QUEEN_CASTLE {
+            self.move_piece(to_sq - 2, to_sq + 1);
+        }
+
+        self.board_state.2 &=
+            CastlingRights::mask_for_square(from_sq) & CastlingRights::mask_for_square(to_sq);
+
+        self.board_state.3 = if flags == MoveFlags::DOUBLE_PAWN_PUSH {
+            Some((from_sq + to_sq) / 2)
+        } else {
+            None
+        };
+
+        if captured.is_some() || piece.get_piece_type() == PieceTypes::PAWN {
+            self.board_state.4 = 0;
+        } else {
+            self.board_state.4 += 1;
+        }
+
+        if color == Color::Black {
+            self.board_state.5 += 1;
+        }
+        self.board_state.1 = opp(color);
+
+        self.hash ^= castling_key(self.board_state.2) ^ self.en_passant_hash() ^ ZOBRIST.side;
+        debug_assert_eq!(self.hash, self.compute_hash(), "incremental hash diverged");
+        debug_assert_eq!(
+            self.pawn_hash,
+            self.compute_pawn_hash(),
+            "incremental pawn hash diverged"
+        );
+    }
+
+    /// Takes back the last move applied with `make_move`
+    pub fn unmake_move(&mut self) {
+        let Undo {
+            mv,
+            captured,
+            castling_rights,
+            en_passant_square,
+            halfmove_clock,
+            hash,
+            pawn_hash,
+        } = self.history.pop().expect("no move to unmake");
+        // The accumulators from before the move are restored as a whole
+        let mut nnue = self.nnue.take();
 
-        // Queen moves
-        enumerate_bits(self.pieces[queen.get_index()], |from_sq| {
-            let att = lookup_queen_att(from_sq, all_pieces_bb) & !ally_pieces_bb;
-            let quiet = att & !opp_pieces_bb;
-            let captures = att & opp_pieces_bb;
+        let color = opp(self.board_state.1);
+        let from_sq = mv.get_from();
+        let to_sq = mv.get_to();
+        let flags = mv.get_flags();
 
-            enumerate_bits(quiet, |to_sq| {
-                moves.push(Move::new(from_sq.into(), to_sq.into(), MoveFlags::QUIET));
-            });
-            enumerate_bits(captures, |to_sq| {
-                moves.push(Move::new(from_sq.into(), to_sq.into(), MoveFlags::CAPTURE));
-            });
+        if mv.is_promotion() {
+            self.remove_piece(to_sq);
+            self.put_piece(Piece::new(color, PieceTypes::PAWN), to_sq);
+        }
+        self.move_piece(to_sq, from_sq);
+
+        if flags == MoveFlags::KING_CASTLE {
+            self.move_piece(to_sq - 1, to_sq + 1);
+        } else if flags == MoveFlags::QUEEN_CASTLE {
+            self.move_piece(to_sq + 1, to_sq - 2);
+        }
+
+        if let Some(captured) = captured {
+            let captured_sq = match (flags == MoveFlags::EP_CAPTURE, color) {
+                (true, Color::White) => to_sq - 8,
+                (true, Color::Black) => to_sq + 8,
+                (false, _) => to_sq,
+            };
+            self.put_piece(captured, captured_sq);
+        }
+
+        if color == Color::Black {
+            self.board_state.5 -= 1;
+        }
+        self.board_state.1 = color;
+        self.board_state.2 = castling_rights;
+        self.board_state.3 = en_passant_square;
+        self.board_state.4 = halfmove_clock;
+        self.hash = hash;
+        self.pawn_hash = pawn_hash;
+        if let Some(nnue) = &mut nnue {
+            nnue.pop();
+        }
+        self.nnue = nnue;
+    }
+
+    /// Passes the move to the opponent without moving a piece, used by null move pruning. Must not
+    /// be called while in check.
+    pub fn make_null_move(&mut self) {
+        debug_assert!(!self.in_check(), "null move while in check");
+        self.history.push(Undo {
+            mv: Move::NULL,
+            captured: None,
+            castling_rights: self.board_state.2,
+            en_passant_square: self.board_state.3,
+            halfmove_clock: self.board_state.4,
+            hash: self.hash,
+            pawn_hash: self.pawn_hash,
         });
 
-        let bishop = Piece::new(ally_color, PieceTypes::BISHOP);
+        self.hash ^= self.en_passant_hash();
+        self.board_state.3 = None;
+        // Positions before the null move do not count as repetitions
+        self.board_state.4 = 0;
+        self.board_state.1 = opp(self.board_state.1);
+        self.hash ^= ZOBRIST.side;
+        debug_assert_eq!(self.hash, self.compute_hash(), "incremental hash diverged");
+    }
+
+    /// Takes back a move made with `make_null_move`
+    pub fn unmake_null_move(&mut self) {
+        let undo = self.history.pop().expect("no null move to unmake");
+        debug_assert!(undo.mv == Move::NULL, "last move is not a null move");
+        self.board_state.1 = opp(self.board_state.1);
+        self.board_state.3 = undo.en_passant_square;
+        self.board_state.4 = undo.halfmove_clock;
+        self.hash = undo.hash;
+    }
+
+    /// Checks if the position is drawn by the fifty-move rule or by repeating an earlier position
+    pub fn is_draw(&self) -> bool {
+        self.board_state.4 >= 100 || self.is_repetition()
+    }
+
+    /// Checks if the position occurred before since the last irreversible move
+    pub fn is_repetition(&self) -> bool {
+        let reversible = self.board_state.4.min(self.history.len());
+        (4..=reversible)
+            .step_by(2)
+            .any(|ply| self.history[self.history.len() - ply].hash == self.hash)
+    }
+
+    /// Returns the Zobrist hash of the position
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+
+    /// Computes the Zobrist hash of the position from scratch
+    pub fn compute_hash(&self) -> u64 {
+        let mut hash = 0;
+        for (square, piece) in self.board_state.0.iter().enumerate() {
+            if let Some(piece) = piece {
+                hash ^= piece_key(*piece, square as u8);
+            }
+        }
+        if self.board_state.1 == Color::Black {
+            hash ^= ZOBRIST.side;
+        }
+        hash ^ castling_key(self.board_state.2) ^ self.en_passant_hash()
+    }
+
+    /// Returns the Zobrist hash of the pawns only, used to cache pawn structure evaluation
+    pub fn pawn_hash(&self) -> u64 {
+        self.pawn_hash
+    }
+
+    /// Computes the pawn hash from scratch
+    pub fn compute_pawn_hash(&self) -> u64 {
+        let mut hash = 0;
+        for (square, piece) in self.board_state.0.iter().enumerate() {
+            if let Some(piece) = piece.filter(|piece| piece.get_piece_type() == PieceTypes::PAWN) {
+                hash ^= piece_key(piece, square as u8);
+            }
+        }
+        hash
+    }
+
+    /// Returns the en passant key if a pawn of the side to move can actually capture en passant,
+    /// so positions only differing by an unusable en passant square hash the same
+    fn en_passant_hash(&self) -> u64 {
+        match self.board_state.3 {
+            Some(ep_sq)
+                if lookup_pawn_att(ep_sq, opp(self.board_state.1))
+                    & self.piece_bb(self.board_state.1, PieceTypes::PAWN)
+                    != 0 =>
+            {
+                en_passant_key(ep_sq)
+            }
+            _ => 0,
+        }
+    }
+
+    /// Places a piece on an empty square
+    fn put_piece(&mut self, piece: Piece, square: u8) {
+        self.hash ^= piece_key(piece, square);
+        if piece.get_piece_type() == PieceTypes::PAWN {
+            self.pawn_hash ^= piece_key(piece, square);
+        }
+        self.pieces[piece.get_index()] |= bb_from_square(square);
+        self.board_state.0[square as usize] = Some(piece);
+        if let Some(nnue) = &mut self.nnue {
+            nnue.add_piece(piece, square);
+        }
+    }
+
+    /// Removes the piece standing on a square
+    fn remove_piece(&mut self, square: u8) {
+        if let Some(piece) = self.board_state.0[square as usize].take() {
+            self.hash ^= piece_key(piece, square);
+            if piece.get_piece_type() == PieceTypes::PAWN {
+                self.pawn_hash ^= piece_key(piece, square);
+            }
+            self.pieces[piece.get_index()] &= !bb_from_square(square);
+            if let Some(nnue) = &mut self.nnue {
+                nnue.remove_piece(piece, square);
+            }
+        }
+    }
 
-        // Bishop moves
-        enumerate_bits(self.pieces[bishop.get_index()], |from_sq| {
-            let att = lookup_bishop_att(from_sq, all_pieces_bb) & !ally_pieces_bb;
-            let quiet = att & !opp_pieces_bb;
-            let captures = att & opp_pieces_bb;
+    /// Moves the piece standing on a square to an empty square
+    fn move_piece(&mut self, from_sq: u8, to_sq: u8) {
+        if let Some(piece) = self.board_state.0[from_sq as usize].take() {
+            self.hash ^= piece_key(piece, from_sq) ^ piece_key(piece, to_sq);
+            if piece.get_piece_type() == PieceTypes::PAWN {
+                self.pawn_hash ^= piece_key(piece, from_sq) ^ piece_key(piece, to_sq);
+            }
+            self.pieces[piece.get_index()] ^= bb_from_square(from_sq) | bb_from_square(to_sq);
+            self.board_state.0[to_sq as usize] = Some(piece);
+            if let Some(nnue) = &mut self.nnue {
+                nnue.move_piece(piece, from_sq, to_sq);
+            }
+        }
+    }
 
-            enumerate_bits(quiet, |to_sq| {
-                moves.push(Move::new(from_sq.into(), to_sq.into(), MoveFlags::QUIET));
-            });
-            enumerate_bits(captures, |to_sq| {
-                moves.push(Move::new(from_sq.into(), to_sq.into(), MoveFlags::CAPTURE));
-            });
+    /// Starts or stops keeping network accumulators up to date. They are computed from scratch
+    /// for the current position.
+    pub fn set_network(&mut self, network: Option<Arc<Network>>) {
+        self.nnue = network.map(|network| {
+            let pieces = self
+                .board_state
+                .0
+                .iter()
+                .enumerate()
+                .filter_map(|(square, piece)| piece.map(|piece| (piece, square as u8)));
+            Box::new(NnueState::new(network, pieces))
         });
+    }
+
+    /// Returns the network accumulators if a network is used for evaluation
+    pub fn nnue(&self) -> Option<&NnueState> {
+        self.nnue.as_deref()
+    }
+
+    /// Returns the piece standing on a square
+    pub fn piece_at(&self, square: u8) -> Option<Piece> {
+        self.board_state.0[square as usize]
+    }
+
+    /// Returns the color of the side to move
+    pub fn side_to_move(&self) -> Color {
+        self.board_state.1
+    }
+
+    /// Returns the castling rights of both sides
+    pub fn castling_rights(&self) -> CastlingRights {
+        self.board_state.2
+    }
+
+    /// Returns the en passant target square
+    pub fn en_passant_square(&self) -> EnPassantSquare {
+        self.board_state.3
+    }
 
-        let rook = Piece::new(ally_color, PieceTypes::ROOK);
+    /// Returns the number of halfmoves since the last capture or pawn move
+    pub fn halfmove_clock(&self) -> HalfMoveClock {
+        self.board_state.4
+    }
+
+    /// Returns the Edward-Forsyth-Notation of the position
+    pub fn to_fen(&self) -> String {
+        format_fen(&self.board_state)
+    }
+
+    /// Returns the bitboard of a single piece kind
+    pub fn piece_bb(&self, color: Color, piece_type: PieceTypes) -> Bitboard {
+        self.pieces[Piece::new(color, piece_type).get_index()]
+    }
 
-        // Rook moves
-        enumerate_bits(self.pieces[rook.get_index()], |from_sq| {
-            let att = lookup_rook_att(from_sq, all_pieces_bb) & !ally_pieces_bb;
-            let quiet = att & !opp_pieces_bb;
-            let captures = att & opp_pieces_bb;
+    /// Returns the square of the king of the given color
+    pub fn king_square(&self, color: Color) -> u8 {
+        self.piece_bb(color, PieceTypes::KING).trailing_zeros() as u8
+    }
+
+    /// Returns all pieces of both colors attacking a square with the given occupancy
+    pub fn attackers_to(&self, square: u8, occupancy: Bitboard) -> Bitboard {
+        let bishops_queens = self.piece_bb(Color::White, PieceTypes::BISHOP)
+            | self.piece_bb(Color::Black, PieceTypes::BISHOP)
+            | self.piece_bb(Color::White, PieceTypes::QUEEN)
+            | self.piece_bb(Color::Black, PieceTypes::QUEEN);
+        let rooks_queens = self.piece_bb(Color::White, PieceTypes::ROOK)
+            | self.piece_bb(Color::Black, PieceTypes::ROOK)
+            | self.piece_bb(Color::White, PieceTypes::QUEEN)
+            | self.piece_bb(Color::Black, PieceTypes::QUEEN);
+
+        (lookup_pawn_att(square, Color::Black) & self.piece_bb(Color::White, PieceTypes::PAWN))
+            | (lookup_pawn_att(square, Color::White)
+                & self.piece_bb(Color::Black, PieceTypes::PAWN))
+            | (lookup_knight_att(square)
+                & (self.piece_bb(Color::White, PieceTypes::KNIGHT)
+                    | self.piece_bb(Color::Black, PieceTypes::KNIGHT)))
+            | (lookup_king_att(square)
+                & (self.piece_bb(Color::White, PieceTypes::KING)
+                    | self.piece_bb(Color::Black, PieceTypes::KING)))
+            | (lookup_bishop_att(square, occupancy) & bishops_queens)
+            | (lookup_rook_att(square, occupancy) & rooks_queens)
+    }
 
-            enumerate_bits(quiet, |to_sq| {
-                moves.push(Move::new(from_sq.into(), to_sq.into(), MoveFlags::QUIET));
-            });
-            enumerate_bits(captures, |to_sq| {
-                moves.push(Move::new(from_sq.into(), to_sq.into(), MoveFlags::CAPTURE));
-            });
+    /// Returns the pieces of the given color that are absolutely pinned to their king
+    pub fn pinned_pieces(&self, color: Color) -> Bitboard {
+        let opp_color = opp(color);
+        let king_sq = self.king_square(color);
+        let ally_pieces_bb = self.all_pieces_bb(color.into());
+        let all_pieces_bb = self.all_pieces_bb(None);
+
+        let queens = self.piece_bb(opp_color, PieceTypes::QUEEN);
+        let snipers = (lookup_rook_att(king_sq, 0)
+            & (self.piece_bb(opp_color, PieceTypes::ROOK) | queens))
+            | (lookup_bishop_att(king_sq, 0)
+                & (self.piece_bb(opp_color, PieceTypes::BISHOP) | queens));
+
+        let mut pinned = 0;
+        enumerate_bits(snipers, |sniper_sq| {
+            let blockers = lookup_between(king_sq, sniper_sq) & all_pieces_bb;
+            if blockers.count_ones() == 1 {
+                pinned |= blockers & ally_pieces_bb;
+            }
         });
+        pinned
+    }
+
+    /// Checks if the king of the side to move is attacked
+    pub fn in_check(&self) -> bool {
+        self.king_attacked(self.board_state.1)
+    }
+
+    /// Checks if the king of the side not to move is attacked. No legal move leads to such a
+    /// position, the king could be captured.
+    pub fn opponent_in_check(&self) -> bool {
+        self.king_attacked(opp(self.board_state.1))
+    }
+
+    fn king_attacked(&self, color: Color) -> bool {
+        let king_sq = self.king_square(color);
+        self.attackers_to(king_sq, self.all_pieces_bb(None)) & self.all_pieces_bb(opp(color).into())
+            != 0
     }
 
     /// Creates a bitboard containing all the pieces from one color or both
@@ -211,10 +481,137 @@ impl Position {
                 }
                 bb
             }
-            _ => {
-                Self::all_pieces_bb(&self, Some(Color::White))
-                    | Self::all_pieces_bb(&self, Some(Color::Black))
+            _ => self.all_pieces_bb(Some(Color::White)) | self.all_pieces_bb(Some(Color::Black)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::attacks::init_tables;
+
+    use super::*;
+
+    #[test]
+    fn make_and_unmake_every_move_correctly() {
+        init_tables();
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "4k3/8/8/2PpP3/8/8/8/4K3 w - d6 0 1",
+            "r3k2r/1P4P1/8/8/8/8/1p4p1/R3K2R b KQkq - 3 40",
+        ] {
+            let mut position = Position::from(fen);
+            let pieces = position.pieces;
+            let side_to_move = position.side_to_move();
+
+            for &mv in position.generate_moves().iter() {
+                position.make_move(mv);
+                assert!(position.side_to_move() != side_to_move);
+                position.unmake_move();
+
+                assert!(position.to_fen() == fen);
+                assert!(position.pieces == pieces);
             }
         }
     }
+
+    #[test]
+    fn update_state_when_making_moves() {
+        init_tables();
+        let mut position = Position::from("r3k2r/8/8/8/4p3/8/3P4/R3K2R w KQkq - 0 1");
+
+        position.make_move(Move::new(11, 27, MoveFlags::DOUBLE_PAWN_PUSH));
+        assert!(position.to_fen() == "r3k2r/8/8/8/3Pp3/8/8/R3K2R b KQkq d3 0 1");
+
+        position.make_move(Move::new(28, 19, MoveFlags::EP_CAPTURE));
+        assert!(position.to_fen() == "r3k2r/8/8/8/8/3p4/8/R3K2R w KQkq - 0 2");
+
+        position.make_move(Move::new(4, 6, MoveFlags::KING_CASTLE));
+        assert!(position.to_fen() == "r3k2r/8/8/8/8/3p4/8/R4RK1 b kq - 1 2");
+
+        position.make_move(Move::new(63, 7, MoveFlags::QUIET));
+        assert!(position.to_fen() == "r3k3/8/8/8/8/3p4/8/R4RKr w q - 2 3");
+
+        position.unmake_move();
+        position.unmake_move();
+        position.unmake_move();
+        position.unmake_move();
+        assert!(position.to_fen() == "r3k2r/8/8/8/4p3/8/3P4/R3K2R w KQkq - 0 1");
+
+        position.make_move(Move::new(11, 27, MoveFlags::DOUBLE_PAWN_PUSH));
+        let hash = position.hash();
+        position.make_null_move();
+        assert!(position.to_fen() == "r3k2r/8/8/8/3Pp3/8/8/R3K2R w KQkq - 0 1");
+        assert!(position.hash() == position.compute_hash());
+        position.unmake_null_move();
+        assert!(position.to_fen() == "r3k2r/8/8/8/3Pp3/8/8/R3K2R b KQkq d3 0 1");
+        assert!(position.hash() == hash);
+    }
+
+    #[test]
+    fn hash_transpositions_identically() {
+        init_tables();
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        let mut a = Position::from(start);
+        let mut b = Position::from(start);
+        let start_hash = a.hash();
+
+        // 1. Nf3 Nf6 2. Nc3 Nc6 and 1. Nc3 Nc6 2. Nf3 Nf6
+        for (from, to) in [(6, 21), (62, 45), (1, 18), (57, 42)] {
+            a.make_move(Move::new(from, to, MoveFlags::QUIET));
+        }
+        for (from, to) in [(1, 18), (57, 42), (6, 21), (62, 45)] {
+            b.make_move(Move::new(from, to, MoveFlags::QUIET));
+        }
+        assert!(a.hash() == b.hash());
+        assert!(a.hash() == a.compute_hash());
+        // Only knights moved
+        assert!(a.pawn_hash() == Position::from(start).pawn_hash());
+        assert!(a.hash() != start_hash);
+
+        for _ in 0..4 {
+            a.unmake_move();
+        }
+        assert!(a.hash() == start_hash);
+    }
+
+    #[test]
+    fn hash_side_castling_and_en_passant() {
+        init_tables();
+        let hash = |fen: &str| Position::from(fen).hash();
+
+        assert!(hash("4k3/8/8/8/8/8/8/4K3 w - - 0 1") != hash("4k3/8/8/8/8/8/8/4K3 b - - 0 1"));
+        assert!(hash("r3k3/8/8/8/8/8/8/4K3 b q - 0 1") != hash("r3k3/8/8/8/8/8/8/4K3 b - - 0 1"));
+        // The en passant square only counts when a capture is possible
+        assert!(
+            hash("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1") != hash("4k3/8/8/3pP3/8/8/8/4K3 w - - 0 1")
+        );
+        assert!(
+            hash("4k3/8/8/3p4/8/8/8/4K3 w - d6 0 1") == hash("4k3/8/8/3p4/8/8/8/4K3 w - - 0 1")
+        );
+    }
+
+    #[test]
+    fn detect_repetitions_and_fifty_move_rule() {
+        init_tables();
+        let mut position = Position::from("4k3/8/8/8/8/8/8/R3K3 w - - 0 1");
+
+        // 1. Ra2 Kd8 2. Ra1 Ke8 returns to the starting position
+        for (from, to) in [(0, 8), (60, 59), (8, 0), (59, 60)] {
+            assert!(!position.is_draw());
+            position.make_move(Move::new(from, to, MoveFlags::QUIET));
+        }
+        assert!(position.is_repetition());
+        position.unmake_move();
+        assert!(!position.is_repetition());
+
+        // Passing the move does not repeat the position
+        position.make_null_move();
+        assert!(!position.is_repetition());
+        position.unmake_null_move();
+
+        assert!(Position::from("4k3/8/8/8/8/8/8/R3K3 w - - 100 80").is_draw());
+        assert!(!Position::from("4k3/8/8/8/8/8/8/R3K3 w - - 99 80").is_draw());
+    }
 }
@@ -0,0 +1,1159 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        LazyLock,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    cmove::{Move, MoveFlags},
+    evaluation::Evaluator,
+    history::{ContinuationKey, History, Killers, OrderingScorer},
+    limits::SearchLimits,
+    move_picker::MovePicker,
+    piece::PieceTypes,
+    position::Position,
+    see::SEE_VALUES,
+    transposition_table::{Bound, TranspositionTable, TtEntry},
+    uci::send,
+    util::Color,
+};
+
+/// Score bound above every reachable score
+pub const INFINITY: i32 = 32_000;
+/// Score of delivering mate at the root, mates further away score lower
+pub const MATE: i32 = 31_000;
+/// Maximum search depth in plies
+pub const MAX_PLY: usize = 128;
+/// Scores beyond this bound encode a forced mate
+pub const MATE_BOUND: i32 = MATE - MAX_PLY as i32;
+
+/// Safety margin of delta pruning for positional gains of a capture
+const DELTA_MARGIN: i32 = 200;
+
+/// Number of nodes searched between checks of the stop flag and the time limit
+const CHECK_INTERVAL: u64 = 2048;
+
+/// Half width of the first aspiration window around the score of the previous iteration
+const ASPIRATION_WINDOW: i32 = 25;
+/// Minimum depth from which iterations start with an aspiration window
+const ASPIRATION_MIN_DEPTH: usize = 4;
+/// Aspiration windows growing beyond this half width are opened completely
+const ASPIRATION_MAX_WINDOW: i32 = 1000;
+
+/// Number of failed quiet moves per node whose history is lowered after a cutoff
+const MAX_QUIETS_TRIED: usize = 64;
+
+/// Minimum depth of null move pruning
+const NMP_MIN_DEPTH: usize = 3;
+/// Maximum depth of reverse futility pruning
+const RFP_MAX_DEPTH: usize = 6;
+/// Margin per ply by which the static evaluation has to exceed beta for reverse futility pruning
+const RFP_MARGIN: i32 = 80;
+/// Maximum depth of futility pruning
+const FP_MAX_DEPTH: usize = 6;
+/// Margin of futility pruning at depth 0, grown by `FP_MARGIN` per ply
+const FP_BASE: i32 = 100;
+const FP_MARGIN: i32 = 80;
+/// Maximum depth of late move pruning
+const LMP_MAX_DEPTH: usize = 8;
+/// Minimum depth of late move reductions
+const LMR_MIN_DEPTH: usize = 3;
+/// History score that changes a late move reduction by one ply
+const LMR_HISTORY_DIVISOR: i32 = 8192;
+
+/// Late move reductions indexed by depth and move number, growing with the logarithm of both
+static LMR_TABLE: LazyLock<[[usize; 64]; 64]> = LazyLock::new(|| {
+    let mut table = [[0; 64]; 64];
+    for (depth, row) in table.iter_mut().enumerate().skip(1) {
+        for (move_count, reduction) in row.iter_mut().enumerate().skip(1) {
+            *reduction = (0.75 + (depth as f64).ln() * (move_count as f64).ln() / 2.25) as usize;
+        }
+    }
+    table
+});
+
+/// Selective search techniques which can be switched off one by one, e.g. to measure their
+/// strength in SPRT tests
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchFeatures {
+    pub null_move_pruning: bool,
+    pub late_move_reductions: bool,
+    pub reverse_futility_pruning: bool,
+    pub futility_pruning: bool,
+    pub late_move_pruning: bool,
+    pub check_extensions: bool,
+}
+
+impl Default for SearchFeatures {
+    fn default() -> Self {
+        SearchFeatures {
+            null_move_pruning: true,
+            late_move_reductions: true,
+            reverse_futility_pruning: true,
+            futility_pruning: true,
+            late_move_pruning: true,
+            check_extensions: true,
+        }
+    }
+}
+
+impl SearchFeatures {
+    /// Every feature switched off, leaving a plain alpha-beta search
+    pub const NONE: SearchFeatures = SearchFeatures {
+        null_move_pruning: false,
+        late_move_reductions: false,
+        reverse_futility_pruning: false,
+        futility_pruning: false,
+        late_move_pruning: false,
+        check_extensions: false,
+    };
+}
+
+/// Score for mating the opponent `ply` plies from the root
+pub fn mate_in(ply: usize) -> i32 {
+    MATE - ply as i32
+}
+
+/// Score for being mated `ply` plies from the root
+pub fn mated_in(ply: usize) -> i32 {
+    -MATE + ply as i32
+}
+
+/// Checks if a score encodes a forced mate for either side
+pub fn is_mate_score(score: i32) -> bool {
+    score.abs() >= MATE_BOUND
+}
+
+/// Formats a score for UCI, e.g. `cp 35` or `mate -3` with the distance counted in moves
+pub fn format_score(score: i32) -> String {
+    if !is_mate_score(score) {
+        format!("cp {}", score)
+    } else if score > 0 {
+        format!("mate {}", (MATE - score + 1) / 2)
+    } else {
+        format!("mate {}", -(MATE + score) / 2)
+    }
+}
+
+/// Converts a mate score from distance to the root into distance to the stored position
+fn score_to_tt(score: i32, ply: usize) -> i32 {
+    if score >= MATE_BOUND {
+        score + ply as i32
+    } else if score <= -MATE_BOUND {
+        score - ply as i32
+    } else {
+        score
+    }
+}
+
+/// Converts a stored mate score back into distance to the root
+fn score_from_tt(score: i32, ply: usize) -> i32 {
+    if score >= MATE_BOUND {
+        score - ply as i32
+    } else if score <= -MATE_BOUND {
+        score + ply as i32
+    } else {
+        score
+    }
+}
+
+/// Returns the stored score if its bound proves it outside of the search window
+fn tt_cutoff(entry: &TtEntry, ply: usize, alpha: i32, beta: i32) -> Option<i32> {
+    let score = score_from_tt(entry.score, ply);
+    let cutoff = match entry.bound {
+        Bound::Exact => score >= beta || score <= alpha,
+        Bound::Lower => score >= beta,
+        Bound::Upper => score <= alpha,
+        Bound::None => false,
+    };
+    cutoff.then_some(score)
+}
+
+/// Score and principal variation of one root move
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PvLine {
+    pub score: i32,
+    pub pv: Vec<Move>,
+}
+
+/// Outcome of a completed search iteration
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchResult {
+    pub best_move: Move,
+    pub score: i32,
+    pub depth: usize,
+    pub nodes: u64,
+    pub pv: Vec<Move>,
+    /// Lines of the best root moves in MultiPV mode, best first. The first line is the one of
+    /// `best_move`.
+    pub lines: Vec<PvLine>,
+}
+
+/// Triangular table holding the principal variation found at every ply
+struct PvTable {
+    moves: [[Move; MAX_PLY]; MAX_PLY],
+    len: [usize; MAX_PLY],
+}
+
+impl PvTable {
+    fn new() -> Self {
+        PvTable {
+            moves: [[Move::NULL; MAX_PLY]; MAX_PLY],
+            len: [0; MAX_PLY],
+        }
+    }
+
+    /// Sets the line at `ply` to `mv` followed by the line found one ply deeper
+    fn update(&mut self, ply: usize, mv: Move) {
+        let child_len = if ply + 1 < MAX_PLY {
+            self.len[ply + 1]
+        } else {
+            0
+        };
+        self.moves[ply][0] = mv;
+        for idx in 0..child_len {
+            self.moves[ply][idx + 1] = self.moves[ply + 1][idx];
+        }
+        self.len[ply] = (child_len + 1).min(MAX_PLY);
+    }
+
+    fn line(&self) -> Vec<Move> {
+        self.moves[0][..self.len[0]].to_vec()
+    }
+}
+
+/// Iterative deepening alpha-beta search over a single position
+pub struct Search<'a> {
+    position: Position,
+    limits: SearchLimits,
+    stop: &'a AtomicBool,
+    tt: &'a TranspositionTable,
+    start: Instant,
+    soft_limit: Option<Duration>,
+    hard_limit: Option<Duration>,
+    /// Legal root moves, the moves of finished lines in MultiPV mode come first
+    root_moves: Vec<Move>,
+    /// Number of best root moves to find lines for
+    multi_pv: usize,
+    /// Index of the line being searched, root moves before it are excluded
+    pv_idx: usize,
+    evaluator: Evaluator,
+    pv: Box<PvTable>,
+    history: Box<History>,
+    killers: Box<Killers>,
+    /// Piece and target square of the move made at every ply on the current path
+    moved: [ContinuationKey; MAX_PLY],
+    features: SearchFeatures,
+    nodes: u64,
+    seldepth: usize,
+    stopped: bool,
+    print_info: bool,
+}
+
+impl<'a> Search<'a> {
+    pub fn new(
+        position: Position,
+        limits: SearchLimits,
+        stop: &'a AtomicBool,
+        tt: &'a TranspositionTable,
+    ) -> Self {
+        let hard_limit = limits.time_budget(position.side_to_move());
+        // Unless the move time is fixed, a new iteration is only started with enough time left
+        let soft_limit = match limits.movetime {
+            Some(_) => hard_limit,
+            None => hard_limit.map(|limit| limit / 2),
+        };
+        let root_moves = root_moves(&position, &limits);
+
+        Search {
+            position,
+            limits,
+            stop,
+            tt,
+            start: Instant::now(),
+            soft_limit,
+            hard_limit,
+            root_moves,
+            multi_pv: 1,
+            pv_idx: 0,
+            evaluator: Evaluator::new(),
+            pv: Box::new(PvTable::new()),
+            history: Box::default(),
+            killers: Box::default(),
+            moved: [None; MAX_PLY],
+            features: SearchFeatures::default(),
+            nodes: 0,
+            seldepth: 0,
+            stopped: false,
+            print_info: true,
+        }
+    }
+
+    /// Enables or disables the `info` lines printed after each iteration
+    pub fn set_print_info(&mut self, print_info: bool) {
+        self.print_info = print_info;
+    }
+
+    /// Sets the number of best root moves to report lines for
+    pub fn set_multi_pv(&mut self, multi_pv: usize) {
+        self.multi_pv = multi_pv.max(1);
+    }
+
+    /// Selects the selective search techniques to use
+    pub fn set_features(&mut self, features: SearchFeatures) {
+        self.features = features;
+    }
+
+    /// Continues with the move ordering statistics of an earlier search, aged so the results of
+    /// this search weigh more
+    pub fn set_history(&mut self, mut history: Box<History>) {
+        history.age();
+        self.history = history;
+    }
+
+    /// Returns the move ordering statistics to pass on to the next search
+    pub fn into_history(self) -> Box<History> {
+        self.history
+    }
+
+    /// Deepens the search until a limit is hit and returns the last completed iteration
+    pub fn run(&mut self) -> SearchResult {
+        let mut result = SearchResult {
+            best_move: self.root_moves.first().copied().unwrap_or(Move::NULL),
+            score: 0,
+            depth: 0,
+            nodes: 0,
+            pv: Vec::new(),
+            lines: Vec::new(),
+        };
+
+        if self.root_moves.is_empty() {
+            result.score = if self.position.in_check() {
+                mated_in(0)
+            } else {
+                0
+            };
+            self.wait_for_stop();
+            return result;
+        }
+
+        let max_depth = self.limits.depth.unwrap_or(MAX_PLY - 1).min(MAX_PLY - 1);
+        let multi_pv = self.multi_pv.min(self.root_moves.len());
+        for depth in 1..=max_depth {
+            self.seldepth = 0;
+            let mut lines = Vec::with_capacity(multi_pv);
+            for pv_idx in 0..multi_pv {
+                self.pv_idx = pv_idx;
+                let previous = result.lines.get(pv_idx).map(|line| line.score);
+                let score = self.aspiration_search(depth, previous);
+                if self.stopped {
+                    break;
+                }
+
+                // The next line is searched without this move
+                let pv = self.pv.line();
+                if let Some(idx) = self.root_moves.iter().position(|&mv| mv == pv[0]) {
+                    self.root_moves[pv_idx..=idx].rotate_right(1);
+                }
+                lines.push(PvLine { score, pv });
+            }
+
+            // The result of a partial iteration cannot be trusted
+            if self.stopped {
+                break;
+            }
+
+            // Later lines may turn out better than earlier ones, their moves are searched first in
+            // the next iteration
+            lines.sort_by_key(|line| -line.score);
+            for (idx, line) in lines.iter().enumerate() {
+                self.root_moves[idx] = line.pv[0];
+            }
+
+            result = SearchResult {
+                best_move: lines[0].pv[0],
+                score: lines[0].score,
+                depth,
+                nodes: self.nodes,
+                pv: lines[0].pv.clone(),
+                lines,
+            };
+
+            if self.print_info {
+                for (idx, line) in result.lines.iter().enumerate() {
+                    send(&self.info_line(depth, idx, line));
+                }
+            }
+
+            if self.iteration_limit_reached(result.score) {
+                break;
+            }
+        }
+
+        self.wait_for_stop();
+        result
+    }
+
+    /// Searches the root within a window around the score of the previous iteration, which is
+    /// widened on the failing side until the score falls inside
+    fn aspiration_search(&mut self, depth: usize, previous: Option<i32>) -> i32 {
+        let mut delta = ASPIRATION_WINDOW;
+        let (mut alpha, mut beta) = match previous {
+            Some(score) if depth >= ASPIRATION_MIN_DEPTH && !is_mate_score(score) => {
+                (score - delta, score + delta)
+            }
+            _ => (-INFINITY, INFINITY),
+        };
+
+        loop {
+            let score = self.negamax(depth, 0, alpha, beta);
+            if self.stopped {
+                return score;
+            }
+
+            if score <= alpha {
+                beta = (alpha + beta) / 2;
+                alpha = (score - delta).max(-INFINITY);
+            } else if score >= beta {
+                beta = (score + delta).min(INFINITY);
+            } else {
+                return score;
+            }
+
+            delta *= 2;
+            if delta > ASPIRATION_MAX_WINDOW {
+                alpha = -INFINITY;
+                beta = INFINITY;
+            }
+        }
+    }
+
+    fn negamax(&mut self, mut depth: usize, ply: usize, mut alpha: i32, mut beta: i32) -> i32 {
+        // Check extension: a position in check is never left to the quiescence search
+        let in_check = self.position.in_check();
+        if in_check && self.features.check_extensions && ply < MAX_PLY / 2 {
+            depth += 1;
+        }
+        if depth == 0 {
+            return self.quiescence(ply, 0, alpha, beta);
+        }
+
+        self.pv.len[ply] = 0;
+        self.seldepth = self.seldepth.max(ply);
+        if self.should_stop() {
+            return 0;
+        }
+        self.nodes += 1;
+
+        let root = ply == 0;
+        if !root {
+            if self.position.is_draw() {
+                return 0;
+            }
+
+            // Mate distance pruning: no line from here can beat a shorter mate already found
+            alpha = alpha.max(mated_in(ply));
+            beta = beta.min(mate_in(ply + 1));
+            if alpha >= beta {
+                return alpha;
+            }
+        }
+
+        if ply >= MAX_PLY - 1 {
+            return self.evaluator.evaluate(&self.position);
+        }
+
+        // Nodes searched with a null window only need a bound, on the principal variation the
+        // table may not cut the line short
+        let pv_node = beta - alpha > 1;
+        let hash = self.position.hash();
+        let tt_entry = self.tt.probe(hash);
+        if let Some(entry) = tt_entry {
+            if !pv_node && entry.depth >= depth {
+                if let Some(score) = tt_cutoff(&entry, ply, alpha, beta) {
+                    return score;
+                }
+            }
+        }
+        let tt_move = tt_entry.map_or(Move::NULL, |entry| entry.mv);
+        let static_eval = tt_entry.map_or_else(
+            || self.evaluator.evaluate(&self.position),
+            |entry| entry.eval,
+        );
+
+        if !root && !pv_node && !in_check {
+            // Reverse futility pruning: the static evaluation beats beta by a margin which a quiet
+            // move of the opponent is unlikely to make up
+            if self.features.reverse_futility_pruning
+                && depth <= RFP_MAX_DEPTH
+                && !is_mate_score(beta)
+                && static_eval - RFP_MARGIN * depth as i32 >= beta
+            {
+                return static_eval;
+            }
+
+            // Null move pruning: if passing still beats beta, a real move will as well. Zugzwang
+            // makes passing the best option in pawn endings, so they are excluded, as is passing
+            // twice in a row.
+            if self.features.null_move_pruning
+                && depth >= NMP_MIN_DEPTH
+                && static_eval >= beta
+                && self.moved[ply - 1].is_some()
+                && has_non_pawn_material(&self.position, self.position.side_to_move())
+            {
+                let reduction = 3 + depth / 4;
+                self.moved[ply] = None;
+                self.position.make_null_move();
+                let score = -self.negamax(
+                    depth.saturating_sub(reduction + 1),
+                    ply + 1,
+                    -beta,
+                    -beta + 1,
+                );
+                self.position.unmake_null_move();
+
+                if self.stopped {
+                    return 0;
+                }
+                if score >= beta {
+                    // Mates found after passing are not proven
+                    return if is_mate_score(score) { beta } else { score };
+                }
+            }
+        }
+
+        let original_alpha = alpha;
+        let mut best_score = -INFINITY;
+        let mut best_move = Move::NULL;
+        let mut move_count = 0;
+        let mut quiets_tried = [Move::NULL; MAX_QUIETS_TRIED];
+        let mut quiet_count = 0;
+        let previous = self.previous_moves(ply);
+        let mut picker = MovePicker::new(&self.position, tt_move, self.killers.get(ply));
+        if ply + 1 < MAX_PLY {
+            self.killers.clear(ply + 1);
+        }
+
+        loop {
+            let mv = if root {
+                match self.root_moves.get(self.pv_idx + move_count) {
+                    Some(&mv) => mv,
+                    None => break,
+                }
+            } else {
+                let scorer = OrderingScorer::new(&self.history, previous);
+                match picker.next(&self.position, &scorer) {
+                    Some(mv) => mv,
+                    None => break,
+                }
+            };
+            move_count += 1;
+            let quiet = !mv.is_capture() && !mv.is_promotion();
+            let moved = self
+                .position
+                .piece_at(mv.get_from())
+                .map(|piece| (piece, mv.get_to()));
+
+            // Shallow quiet moves are pruned once a move avoids getting mated
+            if !root && !in_check && quiet && best_score > -MATE_BOUND {
+                // Late move pruning: late quiet moves rarely beat the moves ordered before them
+                if self.features.late_move_pruning
+                    && !pv_node
+                    && depth <= LMP_MAX_DEPTH
+                    && move_count > 3 + depth * depth
+                {
+                    picker.skip_quiets();
+                    continue;
+                }
+
+                // Futility pruning: the static evaluation is too far below alpha for a quiet move
+                // to catch up
+                if self.features.futility_pruning
+                    && depth <= FP_MAX_DEPTH
+                    && static_eval + FP_BASE + FP_MARGIN * depth as i32 <= alpha
+                {
+                    picker.skip_quiets();
+                    continue;
+                }
+            }
+
+            let history_score = match moved {
+                Some((piece, _)) if quiet => self.history.quiet_score(piece, mv, previous),
+                _ => 0,
+            };
+            self.moved[ply] = moved;
+            self.position.make_move(mv);
+
+            // Late move reductions: quiet moves ordered late are searched with a reduced depth
+            // first, only if they beat alpha they are searched to the full depth
+            let mut reduction = 0;
+            if self.features.late_move_reductions
+                && depth >= LMR_MIN_DEPTH
+                && move_count > 1 + root as usize
+                && quiet
+                && !in_check
+            {
+                let mut r = LMR_TABLE[depth.min(63)][move_count.min(63)] as i32;
+                r -= history_score / LMR_HISTORY_DIVISOR;
+                r -= pv_node as i32;
+                r -= self.position.in_check() as i32;
+                r -= self.killers.get(ply).contains(&mv) as i32;
+                reduction = r.clamp(0, depth as i32 - 2) as usize;
+            }
+
+            // Principal variation search: after the first move, moves are only expected to fail
+            // low and are searched with a null window. The full window is needed only if a move
+            // beats alpha in a PV node.
+            let mut score = 0;
+            if move_count == 1 {
+                score = -self.negamax(depth - 1, ply + 1, -beta, -alpha);
+            } else {
+                if reduction > 0 {
+                    score = -self.negamax(depth - 1 - reduction, ply + 1, -alpha - 1, -alpha);
+                }
+                if reduction == 0 || score > alpha {
+                    score = -self.negamax(depth - 1, ply + 1, -alpha - 1, -alpha);
+                }
+                if pv_node && score > alpha && score < beta {
+                    score = -self.negamax(depth - 1, ply + 1, -beta, -alpha);
+                }
+            }
+            self.position.unmake_move();
+
+            if self.stopped {
+                return 0;
+            }
+
+            if score > best_score {
+                best_score = score;
+                if score > alpha {
+                    alpha = score;
+                    best_move = mv;
+                    self.pv.update(ply, mv);
+                    if score >= beta {
+                        if quiet {
+                            self.killers.store(ply, mv);
+                            self.history.update_quiets(
+                                &self.position,
+                                mv,
+                                &quiets_tried[..quiet_count],
+                                previous,
+                                depth,
+                            );
+                        }
+                        break;
+                    }
+                }
+            }
+            if quiet && quiet_count < MAX_QUIETS_TRIED {
+                quiets_tried[quiet_count] = mv;
+                quiet_count += 1;
+            }
+        }
+
+        if move_count == 0 {
+            return if in_check { mated_in(ply) } else { 0 };
+        }
+
+        let bound = if best_score >= beta {
+            Bound::Lower
+        } else if alpha > original_alpha {
+            Bound::Exact
+        } else {
+            Bound::Upper
+        };
+        // Later MultiPV lines exclude the best root moves, so only the first line may describe
+        // the root position
+        if !root || self.pv_idx == 0 {
+            self.tt.store(
+                hash,
+                best_move,
+                score_to_tt(best_score, ply),
+                static_eval,
+                depth,
+                bound,
+            );
+        }
+        best_score
+    }
+
+    /// Resolves captures and promotions until the position is quiet, so the static evaluation is
+    /// not taken in the middle of an exchange. `qply` counts the plies since the main search.
+    fn quiescence(&mut self, ply: usize, qply: usize, mut alpha: i32, beta: i32) -> i32 {
+        self.pv.len[ply] = 0;
+        self.seldepth = self.seldepth.max(ply);
+        if self.should_stop() {
+            return 0;
+        }
+        self.nodes += 1;
+        if ply > 0 && self.position.is_draw() {
+            return 0;
+        }
+        if ply >= MAX_PLY - 1 {
+            return self.evaluator.evaluate(&self.position);
+        }
+
+        let hash = self.position.hash();
+        let tt_entry = self.tt.probe(hash);
+        if let Some(entry) = tt_entry {
+            if let Some(score) = tt_cutoff(&entry, ply, alpha, beta) {
+                return score;
+            }
+        }
+        let tt_move = tt_entry.map_or(Move::NULL, |entry| entry.mv);
+
+        // Standing pat is no option when in check, so all evasions are searched on the first ply.
+        // Deeper checks are only caused by captures and are not resolved to bound the search.
+        let evading = qply == 0 && self.position.in_check();
+        // The static evaluation is stored even when evading, since the position may be reached
+        // again by a checking capture where standing pat is allowed
+        let static_eval = tt_entry.map_or_else(
+            || self.evaluator.evaluate(&self.position),
+            |entry| entry.eval,
+        );
+        let stand_pat = if evading { -INFINITY } else { static_eval };
+        if stand_pat >= beta {
+            return stand_pat;
+        }
+
+        let original_alpha = alpha;
+        alpha = alpha.max(stand_pat);
+        let mut best_score = stand_pat;
+        let mut best_move = Move::NULL;
+        let mut move_count = 0;
+        let previous = self.previous_moves(ply);
+        let mut picker = MovePicker::new_qsearch(&self.position, tt_move, evading);
+
+        while let Some(mv) = picker.next(
+            &self.position,
+            &OrderingScorer::new(&self.history, previous),
+        ) {
+            move_count += 1;
+
+            // Delta pruning: skip captures that cannot raise the score to alpha even with a margin
+            if !evading
+                && !mv.is_promotion()
+                && stand_pat + captured_value(&self.position, mv) + DELTA_MARGIN <= alpha
+            {
+                continue;
+            }
+
+            self.moved[ply] = self
+                .position
+                .piece_at(mv.get_from())
+                .map(|piece| (piece, mv.get_to()));
+            self.position.make_move(mv);
+            let score = -self.quiescence(ply + 1, qply + 1, -beta, -alpha);
+            self.position.unmake_move();
+
+            if self.stopped {
+                return 0;
+            }
+
+            if score > best_score {
+                best_score = score;
+                if score > alpha {
+                    alpha = score;
+                    best_move = mv;
+                    self.pv.update(ply, mv);
+                    if score >= beta {
+                        break;
+                    }
+                }
+            }
+        }
+
+        if evading && move_count == 0 {
+            return mated_in(ply);
+        }
+
+        let bound = if best_score >= beta {
+            Bound::Lower
+        } else if alpha > original_alpha && best_move != Move::NULL {
+            Bound::Exact
+        } else {
+            Bound::Upper
+        };
+        self.tt.store(
+            hash,
+            best_move,
+            score_to_tt(best_score, ply),
+            static_eval,
+            0,
+            bound,
+        );
+        best_score
+    }
+
+    /// Returns the moves made one and two plies before `ply`
+    fn previous_moves(&self, ply: usize) -> [ContinuationKey; 2] {
+        let moved = |back: usize| ply.checked_sub(back).and_then(|idx| self.moved[idx]);
+        [moved(1), moved(2)]
+    }
+
+    /// Checks the node limit on every node, the stop flag and time limit every few nodes
+    fn should_stop(&mut self) -> bool {
+        if self.stopped {
+            return true;
+        }
+        if self.limits.nodes.is_some_and(|nodes| self.nodes >= nodes) {
+            self.stopped = true;
+            return true;
+        }
+        if !self.nodes.is_multiple_of(CHECK_INTERVAL) {
+            return false;
+        }
+
+        self.stopped = self.stop.load(Ordering::Relaxed)
+            || self
+                .hard_limit
+                .is_some_and(|limit| self.start.elapsed() >= limit);
+        self.stopped
+    }
+
+    /// Checks if another iteration should be started after one finished with `score`
+    fn iteration_limit_reached(&self, score: i32) -> bool {
+        if self.stop.load(Ordering::Relaxed) {
+            return true;
+        }
+        // A mate in `mate` moves is a mate in `2 * mate - 1` plies, mates beyond the maximum
+        // depth are never found
+        if let Some(mate) = self.limits.mate {
+            let plies = mate.saturating_mul(2).saturating_sub(1).min(MAX_PLY);
+            if score >= mate_in(plies) {
+                return true;
+            }
+        }
+        if self.limits.nodes.is_some_and(|nodes| self.nodes >= nodes) {
+            return true;
+        }
+        self.soft_limit
+            .is_some_and(|limit| self.start.elapsed() >= limit)
+    }
+
+    /// In infinite mode the best move may only be sent once the GUI asks for it
+    fn wait_for_stop(&self) {
+        while self.limits.infinite && !self.stop.load(Ordering::Relaxed) {
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    /// Formats the `info` line of a principal variation, numbered from 0 in MultiPV mode
+    fn info_line(&self, depth: usize, idx: usize, line: &PvLine) -> String {
+        let elapsed = self.start.elapsed();
+        let nps = (self.nodes as f64 / elapsed.as_secs_f64().max(0.001)) as u64;
+        let pv: Vec<String> = line.pv.iter().map(|mv| mv.to_string()).collect();
+        let multi_pv = if self.multi_pv > 1 {
+            format!(" multipv {}", idx + 1)
+        } else {
+            String::new()
+        };
+        format!(
+            "info depth {} seldepth {}{} score {} nodes {} nps {} hashfull {} time {} pv {}",
+            depth,
+            self.seldepth,
+            multi_pv,
+            format_score(line.score),
+            self.nodes,
+            nps,
+            self.tt.hashfull(),
+            elapsed.as_millis(),
+            pv.join(" ")
+        )
+    }
+}
+
+/// Returns the material value of the piece captured by a move
+pub(crate) fn captured_value(position: &Position, mv: Move) -> i32 {
+    if mv.get_flags() == MoveFlags::EP_CAPTURE {
+        return SEE_VALUES[PieceTypes::PAWN.bits() as usize];
+    }
+    position.piece_at(mv.get_to()).map_or(0, |piece| {
+        SEE_VALUES[piece.get_piece_type().bits() as usize]
+    })
+}
+
+/// Checks if a side has a piece other than its king and pawns
+fn has_non_pawn_material(position: &Position, color: Color) -> bool {
+    [
+        PieceTypes::KNIGHT,
+        PieceTypes::BISHOP,
+        PieceTypes::ROOK,
+        PieceTypes::QUEEN,
+    ]
+    .iter()
+    .any(|&piece_type| position.piece_bb(color, piece_type) != 0)
+}
+
+/// Returns the legal root moves, restricted to `searchmoves` if given
+fn root_moves(position: &Position, limits: &SearchLimits) -> Vec<Move> {
+    position
+        .generate_moves()
+        .iter()
+        .copied()
+        .filter(|mv| limits.searchmoves.is_empty() || limits.searchmoves.contains(&mv.to_string()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::attacks::init_tables;
+
+    use super::*;
+
+    fn search(fen: &str, depth: usize) -> SearchResult {
+        search_with(fen, depth, SearchFeatures::default())
+    }
+
+    fn search_with(fen: &str, depth: usize, features: SearchFeatures) -> SearchResult {
+        let stop = AtomicBool::new(false);
+        let tt = TranspositionTable::new(1);
+        let limits = SearchLimits {
+            depth: Some(depth),
+            ..Default::default()
+        };
+        let mut search = Search::new(Position::from(fen), limits, &stop, &tt);
+        search.set_print_info(false);
+        search.set_features(features);
+        search.run()
+    }
+
+    #[test]
+    fn find_mates_with_distance() {
+        init_tables();
+        // Back rank mate in one
+        let result = search("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", 3);
+        assert!(result.best_move.to_string() == "a1a8");
+        assert!(result.score == mate_in(1));
+        assert!(format_score(result.score) == "mate 1");
+
+        // Being mated scores negative and counts the moves until mate
+        let result = search("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1", 2);
+        assert!(result.score == mated_in(0));
+        assert!(result.best_move == Move::NULL);
+        assert!(format_score(mated_in(4)) == "mate -2");
+
+        // Mate limits out of range do not break the search
+        let stop = AtomicBool::new(false);
+        let tt = TranspositionTable::new(1);
+        for mate in [0, usize::MAX] {
+            let limits = SearchLimits {
+                depth: Some(4),
+                mate: Some(mate),
+                ..Default::default()
+            };
+            let fen = "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1";
+            let mut search = Search::new(Position::from(fen), limits, &stop, &tt);
+            search.set_print_info(false);
+            assert!(search.run().score == mate_in(1));
+        }
+    }
+
+    #[test]
+    fn score_stalemate_as_draw() {
+        init_tables();
+        let result = search("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1", 3);
+        assert!(result.score == 0);
+        assert!(result.best_move == Move::NULL);
+    }
+
+    #[test]
+    fn return_a_legal_principal_variation() {
+        init_tables();
+        let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+        let result = search(fen, 3);
+        assert!(result.depth == 3);
+        assert!(result.pv.len() >= 3);
+        assert!(result.pv[0] == result.best_move);
+
+        let mut position = Position::from(fen);
+        for &mv in &result.pv {
+            assert!(position.generate_moves().contains(mv));
+            position.make_move(mv);
+        }
+    }
+
+    #[test]
+    fn win_hanging_material() {
+        init_tables();
+        let result = search("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1", 2);
+        assert!(result.best_move.to_string() == "d2d5");
+    }
+
+    #[test]
+    fn restrict_root_moves_to_searchmoves() {
+        init_tables();
+        let position = Position::from("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+        let limits = SearchLimits::parse("searchmoves e2e4 g1f3 e2e5");
+        assert!(root_moves(&position, &limits).len() == 2);
+    }
+
+    #[test]
+    fn resolve_exchanges_in_quiescence() {
+        init_tables();
+        // The pawn on d5 is defended, taking it with the queen loses the queen
+        let result = search("4k3/8/4p3/3p4/8/8/3Q4/4K3 w - - 0 1", 1);
+        assert!(result.best_move.to_string() != "d2d5");
+        assert!(result.score > 600);
+
+        // Escaping the check is the only way out of the quiescence search
+        let stop = AtomicBool::new(false);
+        let tt = TranspositionTable::new(1);
+        let mut search = Search::new(
+            Position::from("4k3/8/8/8/8/8/3q4/4K3 w - - 0 1"),
+            SearchLimits::default(),
+            &stop,
+            &tt,
+        );
+        assert!(search.quiescence(0, 0, -INFINITY, INFINITY).abs() < 100);
+        assert!(search.pv.line()[0].to_string() == "e1d2");
+        let entry = tt.probe(search.position.hash()).unwrap();
+        assert!(entry.eval == search.evaluator.evaluate(&search.position));
+    }
+
+    #[test]
+    fn prune_without_losing_tactics() {
+        init_tables();
+        let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+        let full = search_with(fen, 5, SearchFeatures::NONE);
+        let selective = search(fen, 5);
+        assert!(selective.nodes * 2 < full.nodes);
+
+        // Each technique on its own still finds the mate
+        let mate = "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1";
+        for enable in [
+            |features: &mut SearchFeatures| features.null_move_pruning = true,
+            |features: &mut SearchFeatures| features.late_move_reductions = true,
+            |features: &mut SearchFeatures| features.reverse_futility_pruning = true,
+            |features: &mut SearchFeatures| features.futility_pruning = true,
+            |features: &mut SearchFeatures| features.late_move_pruning = true,
+            |features: &mut SearchFeatures| features.check_extensions = true,
+        ] {
+            let mut features = SearchFeatures::NONE;
+            enable(&mut features);
+            assert!(search_with(mate, 5, features).score == mate_in(1));
+        }
+    }
+
+    #[test]
+    fn avoid_null_moves_in_pawn_endings() {
+        init_tables();
+        let position = Position::from("8/8/4k3/4p3/4P3/4K3/8/8 w - - 0 1");
+        assert!(!has_non_pawn_material(&position, Color::White));
+        let position = Position::from("8/8/4k3/4p3/4P3/4K3/8/7N b - - 0 1");
+        assert!(has_non_pawn_material(&position, Color::White));
+        assert!(!has_non_pawn_material(&position, Color::Black));
+    }
+
+    #[test]
+    fn widen_aspiration_windows_until_the_score_fits() {
+        init_tables();
+        let stop = AtomicBool::new(false);
+        let tt = TranspositionTable::new(1);
+        let fen = "4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1";
+        // A guess far too low fails high, one far too high fails low
+        for previous in [-800, 800] {
+            let mut search = Search::new(Position::from(fen), SearchLimits::default(), &stop, &tt);
+            let score = search.aspiration_search(5, Some(previous));
+            assert!(score > 0 && score < 800);
+            assert!(search.pv.line()[0].to_string() == "d2d5");
+            tt.clear();
+        }
+    }
+
+    #[test]
+    fn stop_at_the_node_limit() {
+        init_tables();
+        let stop = AtomicBool::new(false);
+        let tt = TranspositionTable::new(1);
+        let fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        for limit in [300, 5000] {
+            let limits = SearchLimits {
+                nodes: Some(limit),
+                ..Default::default()
+            };
+            let mut search = Search::new(Position::from(fen), limits, &stop, &tt);
+            search.set_print_info(false);
+            let result = search.run();
+            assert!(result.depth > 0 && result.best_move != Move::NULL);
+            assert!(result.nodes <= limit && search.nodes == limit);
+            tt.clear();
+        }
+    }
+
+    #[test]
+    fn report_the_best_lines_in_multi_pv_mode() {
+        init_tables();
+        let stop = AtomicBool::new(false);
+        let tt = TranspositionTable::new(1);
+        let multi_pv_search = |fen: &str, multi_pv: usize| {
+            let mut search = Search::new(
+                Position::from(fen),
+                SearchLimits::parse("depth 5"),
+                &stop,
+                &tt,
+            );
+            search.set_print_info(false);
+            search.set_multi_pv(multi_pv);
+            search.run()
+        };
+
+        let result = multi_pv_search(
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            3,
+        );
+        assert!(result.lines.len() == 3);
+        assert!(result.lines[0].pv == result.pv);
+        assert!(result.lines[0].score == result.score);
+        assert!(result
+            .lines
+            .windows(2)
+            .all(|pair| pair[0].score >= pair[1].score));
+        let first_moves: Vec<Move> = result.lines.iter().map(|line| line.pv[0]).collect();
+        assert!(first_moves
+            .iter()
+            .enumerate()
+            .all(|(idx, mv)| !first_moves[..idx].contains(mv)));
+
+        // Only two legal moves
+        let result = multi_pv_search("4k3/8/8/8/8/8/3q4/4K3 w - - 0 1", 4);
+        assert!(result.lines.len() == 2);
+        assert!(result.best_move.to_string() == "e1d2");
+
+        // The table keeps the root entry of the best line, not of the lines without it
+        tt.clear();
+        let fen = "4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1";
+        let result = multi_pv_search(fen, 3);
+        let entry = tt.probe(Position::from(fen).hash()).unwrap();
+        assert!(entry.mv == result.best_move);
+        assert!(entry.mv.to_string() == "d2d5");
+    }
+
+    #[test]
+    fn store_mate_scores_relative_to_position() {
+        // Mate in 3 plies found 2 plies from the root is a mate in 1 ply from the stored position
+        assert!(score_to_tt(mate_in(3), 2) == mate_in(1));
+        assert!(score_from_tt(mate_in(1), 2) == mate_in(3));
+        assert!(score_to_tt(mated_in(4), 3) == mated_in(1));
+        assert!(score_from_tt(score_to_tt(mated_in(4), 3), 3) == mated_in(4));
+        assert!(score_to_tt(150, 7) == 150);
+
+        // A second search of the same position finds the mate again through the table
+        init_tables();
+        let stop = AtomicBool::new(false);
+        let tt = TranspositionTable::new(1);
+        let fen = "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1";
+        for _ in 0..2 {
+            let limits = SearchLimits::parse("depth 4");
+            let mut search = Search::new(Position::from(fen), limits, &stop, &tt);
+            search.set_print_info(false);
+            let result = search.run();
+            assert!(result.score == mate_in(1));
+            assert!(result.best_move.to_string() == "a1a8");
+            tt.new_search();
+        }
+    }
+}
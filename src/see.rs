@@ -0,0 +1,229 @@
+use crate::{
+    attacks::{lookup_bishop_att, lookup_rook_att},
+    cmove::{Move, MoveFlags},
+    piece::PieceTypes,
+    position::Position,
+    util::{bb_from_square, opp, sq_to_rank, Bitboard, Color},
+};
+
+/// Piece values used for exchanges indexed by piece type. The king can only capture last.
+pub const SEE_VALUES: [i32; 6] = [100, 300, 300, 500, 900, 0];
+
+/// Piece types in the order attackers are swapped off
+const ATTACKER_ORDER: [PieceTypes; 6] = [
+    PieceTypes::PAWN,
+    PieceTypes::KNIGHT,
+    PieceTypes::BISHOP,
+    PieceTypes::ROOK,
+    PieceTypes::QUEEN,
+    PieceTypes::KING,
+];
+
+fn see_value(piece_type: PieceTypes) -> i32 {
+    SEE_VALUES[piece_type.bits() as usize]
+}
+
+impl Position {
+    /// Computes the material balance of the exchange started by a move on its target square,
+    /// assuming both sides may stop capturing whenever it suits them. Pins are ignored.
+    pub fn see(&self, mv: Move) -> i32 {
+        let flags = mv.get_flags();
+        if flags == MoveFlags::KING_CASTLE || flags == MoveFlags::QUEEN_CASTLE {
+            return 0;
+        }
+
+        let from_sq = mv.get_from();
+        let to_sq = mv.get_to();
+        let mut occupancy = self.all_pieces_bb(None) ^ bb_from_square(from_sq);
+
+        let mut gain = [0i32; 32];
+        gain[0] = if flags == MoveFlags::EP_CAPTURE {
+            // The captured pawn is behind the target square and may hide a rook or queen
+            occupancy ^= bb_from_square(to_sq ^ 8);
+            see_value(PieceTypes::PAWN)
+        } else {
+            self.piece_at(to_sq)
+                .map_or(0, |piece| see_value(piece.get_piece_type()))
+        };
+
+        let mut on_square = self.moving_piece_type(mv);
+        if let Some(piece_type) = mv.get_promotion_piece_type() {
+            gain[0] += see_value(piece_type) - see_value(PieceTypes::PAWN);
+            on_square = piece_type;
+        }
+
+        let mut attackers = self.attackers_to(to_sq, occupancy) & occupancy;
+        let mut color = opp(self.side_to_move());
+        let mut depth = 0;
+
+        while let Some((piece_type, from_bb)) = self.least_valuable_attacker(attackers, color) {
+            // The king may not capture onto a defended square
+            if piece_type == PieceTypes::KING
+                && attackers & self.all_pieces_bb(opp(color).into()) != 0
+            {
+                break;
+            }
+
+            depth += 1;
+            gain[depth] = see_value(on_square) - gain[depth - 1];
+            on_square = piece_type;
+            if piece_type == PieceTypes::PAWN && matches!(sq_to_rank(to_sq), 0 | 7) {
+                gain[depth] += see_value(PieceTypes::QUEEN) - see_value(PieceTypes::PAWN);
+                on_square = PieceTypes::QUEEN;
+            }
+
+            occupancy ^= from_bb;
+            attackers = (attackers | self.xray_attackers(to_sq, piece_type, occupancy)) & occupancy;
+            color = opp(color);
+        }
+
+        // Each side only continues the exchange if it does not lose by doing so
+        while depth > 0 {
+            gain[depth - 1] = -(-gain[depth - 1]).max(gain[depth]);
+            depth -= 1;
+        }
+        gain[0]
+    }
+
+    /// Checks if the exchange started by a move gains at least `threshold`
+    pub fn see_ge(&self, mv: Move, threshold: i32) -> bool {
+        let flags = mv.get_flags();
+        if flags == MoveFlags::KING_CASTLE || flags == MoveFlags::QUEEN_CASTLE {
+            return threshold <= 0;
+        }
+
+        let captured = if flags == MoveFlags::EP_CAPTURE {
+            see_value(PieceTypes::PAWN)
+        } else {
+            self.piece_at(mv.get_to())
+                .map_or(0, |piece| see_value(piece.get_piece_type()))
+        };
+        let (best_case, on_square) = match mv.get_promotion_piece_type() {
+            Some(piece_type) => (
+                captured + see_value(piece_type) - see_value(PieceTypes::PAWN),
+                see_value(piece_type),
+            ),
+            None => (captured, see_value(self.moving_piece_type(mv))),
+        };
+
+        // Even keeping the captured piece for free does not reach the threshold
+        if best_case < threshold {
+            return false;
+        }
+        // Even losing the moved piece in return still reaches the threshold
+        if best_case - on_square >= threshold {
+            return true;
+        }
+        self.see(mv) >= threshold
+    }
+
+    fn moving_piece_type(&self, mv: Move) -> PieceTypes {
+        self.piece_at(mv.get_from())
+            .expect("no piece on from square")
+            .get_piece_type()
+    }
+
+    /// Returns the type and square of the cheapest attacker of the given color
+    fn least_valuable_attacker(
+        &self,
+        attackers: Bitboard,
+        color: Color,
+    ) -> Option<(PieceTypes, Bitboard)> {
+        ATTACKER_ORDER.iter().find_map(|&piece_type| {
+            let bb = attackers & self.piece_bb(color, piece_type);
+            (bb != 0).then(|| (piece_type, bb & bb.wrapping_neg()))
+        })
+    }
+
+    /// Returns the sliders revealed behind a piece of the given type leaving its square
+    fn xray_attackers(&self, square: u8, piece_type: PieceTypes, occupancy: Bitboard) -> Bitboard {
+        let mut xrays = 0;
+        if matches!(
+            piece_type,
+            PieceTypes::PAWN | PieceTypes::BISHOP | PieceTypes::QUEEN
+        ) {
+            xrays |= lookup_bishop_att(square, occupancy) & self.diagonal_sliders();
+        }
+        if matches!(piece_type, PieceTypes::ROOK | PieceTypes::QUEEN) {
+            xrays |= lookup_rook_att(square, occupancy) & self.orthogonal_sliders();
+        }
+        xrays
+    }
+
+    fn diagonal_sliders(&self) -> Bitboard {
+        [Color::White, Color::Black].iter().fold(0, |bb, &color| {
+            bb | self.piece_bb(color, PieceTypes::BISHOP) | self.piece_bb(color, PieceTypes::QUEEN)
+        })
+    }
+
+    fn orthogonal_sliders(&self) -> Bitboard {
+        [Color::White, Color::Black].iter().fold(0, |bb, &color| {
+            bb | self.piece_bb(color, PieceTypes::ROOK) | self.piece_bb(color, PieceTypes::QUEEN)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::attacks::init_tables;
+
+    use super::*;
+
+    fn see(fen: &str, uci_move: &str) -> i32 {
+        let position = Position::from(fen);
+        position.see(position.parse_move(uci_move).unwrap())
+    }
+
+    #[test]
+    fn evaluate_exchanges_correctly() {
+        init_tables();
+        // Undefended pawn
+        assert!(see("1k1r4/1pp4p/p7/4p3/8/P5P1/1PP4P/2K1R3 w - - 0 1", "e1e5") == 100);
+        // Batteries behind both knights, the knight is lost for a pawn
+        assert!(
+            see(
+                "1k1r3q/1ppn3p/p4b2/4p3/8/P2N2P1/1PP1R1BP/2K1Q3 w - - 0 1",
+                "d3e5"
+            ) == -200
+        );
+        // A rook behind the capturing rook makes the recapture unprofitable
+        assert!(see("3rk3/8/8/3p4/8/8/3R4/3RK3 w - - 0 1", "d2d5") == 100);
+        assert!(see("3rk3/8/8/3p4/8/8/3R4/4K3 w - - 0 1", "d2d5") == -400);
+        // Pawns recapturing on the last rank promote
+        assert!(see("4k3/8/8/8/8/8/2p5/Rn2K3 w - - 0 1", "a1b1") == -1000);
+        // A defended king cannot recapture
+        assert!(see("4k3/4q3/8/8/8/8/4R3/5K2 w - - 0 1", "e2e7") == 400);
+        assert!(see("4k3/4q3/8/8/8/8/4R3/4QK2 w - - 0 1", "e2e7") == 900);
+        assert!(see("4k3/4q3/8/8/8/8/4Q3/4K3 w - - 0 1", "e2e7") == 0);
+    }
+
+    #[test]
+    fn evaluate_en_passant_and_promotions_correctly() {
+        init_tables();
+        assert!(see("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5d6") == 100);
+        // The rook wins the capturing pawn back
+        assert!(see("3rk3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5d6") == 0);
+        assert!(see("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1", "b7b8q") == 800);
+        assert!(see("1r2k3/P7/8/8/8/8/8/4K3 w - - 0 1", "a7a8q") == -100);
+        assert!(see("1rk5/P7/8/8/8/8/8/4K3 w - - 0 1", "a7b8q") == 400);
+        assert!(see("1rk5/P7/8/8/8/8/8/4K3 w - - 0 1", "a7b8n") == 400);
+    }
+
+    #[test]
+    fn compare_with_threshold_consistently() {
+        init_tables();
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "1k1r3q/1ppn3p/p4b2/4p3/8/P2N2P1/1PP1R1BP/2K1Q3 w - - 0 1",
+            "r2q1rk1/pP1p2pp/Q4n2/bbp1p3/Np6/1B3NBn/pPPP1PPP/R3K2R b KQ - 0 1",
+        ] {
+            let position = Position::from(fen);
+            for &mv in position.generate_moves().iter() {
+                let see = position.see(mv);
+                for threshold in (-1000..=1000).step_by(50) {
+                    assert!(position.see_ge(mv, threshold) == (see >= threshold));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,327 @@
+use std::{
+    mem::size_of,
+    sync::atomic::{AtomicU64, AtomicU8, Ordering},
+};
+
+use crate::cmove::Move;
+
+/// Number of entries sharing a cache line
+const BUCKET_SIZE: usize = 4;
+/// Generations are stored in 6 bits and wrap around
+const GENERATION_MASK: u8 = 0b11_1111;
+/// A non-exact result of the same search replaces an entry of the same position only if it is
+/// at most this much shallower
+const REPLACE_DEPTH_MARGIN: usize = 3;
+
+/// Relation of a stored score to the true score of the position
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Bound {
+    None = 0,
+    /// The true score is at most the stored score, no move reached alpha
+    Upper = 1,
+    /// The true score is at least the stored score, a move failed high
+    Lower = 2,
+    Exact = 3,
+}
+
+/// Unpacked contents of a table entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TtEntry {
+    pub mv: Move,
+    pub score: i32,
+    pub eval: i32,
+    pub depth: usize,
+    pub bound: Bound,
+}
+
+/// A single entry of two words. The key is stored xor-ed with the data, so an entry torn by
+/// concurrent writes of two threads fails verification instead of returning mixed up data.
+#[derive(Default)]
+struct Slot {
+    key: AtomicU64,
+    data: AtomicU64,
+}
+
+/// Entries with a hash mapping to the same index, aligned to occupy exactly one cache line
+#[derive(Default)]
+#[repr(align(64))]
+struct Bucket {
+    slots: [Slot; BUCKET_SIZE],
+}
+
+/// Hash table of search results shared by all search threads. Writes are lockless, a lost or
+/// rejected write only costs search efficiency.
+pub struct TranspositionTable {
+    buckets: Vec<Bucket>,
+    generation: AtomicU8,
+}
+
+impl TranspositionTable {
+    /// Creates a table using about `mb` megabytes
+    pub fn new(mb: usize) -> Self {
+        let mut table = TranspositionTable {
+            buckets: Vec::new(),
+            generation: AtomicU8::new(0),
+        };
+        table.resize(mb);
+        table
+    }
+
+    /// Reallocates the table with about `mb` megabytes, dropping all entries
+    pub fn resize(&mut self, mb: usize) {
+        let len = (mb * 1024 * 1024 / size_of::<Bucket>()).max(1);
+        self.buckets = Vec::new();
+        self.buckets.resize_with(len, Bucket::default);
+        self.generation.store(0, Ordering::Relaxed);
+    }
+
+    /// Removes all entries
+    pub fn clear(&self) {
+        for slot in self.buckets.iter().flat_map(|bucket| &bucket.slots) {
+            slot.key.store(0, Ordering::Relaxed);
+            slot.data.store(0, Ordering::Relaxed);
+        }
+        self.generation.store(0, Ordering::Relaxed);
+    }
+
+    /// Ages the existing entries, to be called before each new search
+    pub fn new_search(&self) {
+        let generation = self.generation.load(Ordering::Relaxed);
+        self.generation
+            .store((generation + 1) & GENERATION_MASK, Ordering::Relaxed);
+    }
+
+    /// Returns the size of the table in megabytes
+    pub fn size_mb(&self) -> usize {
+        self.buckets.len() * size_of::<Bucket>() / (1024 * 1024)
+    }
+
+    /// Looks up the entry stored for a position hash
+    pub fn probe(&self, hash: u64) -> Option<TtEntry> {
+        self.bucket(hash).slots.iter().find_map(|slot| {
+            let data = slot.data.load(Ordering::Relaxed);
+            let key = slot.key.load(Ordering::Relaxed);
+            (data != 0 && key ^ data == hash).then(|| unpack(data))
+        })
+    }
+
+    /// Stores a search result. An entry of the same position is overwritten by exact, deep
+    /// enough or newer results, otherwise the shallowest and oldest entry of the bucket is
+    /// replaced.
+    pub fn store(&self, hash: u64, mv: Move, score: i32, eval: i32, depth: usize, bound: Bound) {
+        let generation = self.generation.load(Ordering::Relaxed);
+        let bucket = self.bucket(hash);
+
+        let mut replace = &bucket.slots[0];
+        let mut replace_value = i32::MAX;
+        for slot in &bucket.slots {
+            let data = slot.data.load(Ordering::Relaxed);
+            let key = slot.key.load(Ordering::Relaxed);
+            if data == 0 || key ^ data == hash {
+                replace = slot;
+                break;
+            }
+
+            let age = generation.wrapping_sub(data_generation(data)) & GENERATION_MASK;
+            let value = data_depth(data) as i32 - 8 * age as i32;
+            if value < replace_value {
+                replace = slot;
+                replace_value = value;
+            }
+        }
+
+        let old_data = replace.data.load(Ordering::Relaxed);
+        let old_key = replace.key.load(Ordering::Relaxed);
+        let data = if old_data != 0 && old_key ^ old_data == hash {
+            let old = unpack(old_data);
+            if bound == Bound::Exact
+                || depth + REPLACE_DEPTH_MARGIN >= old.depth
+                || data_generation(old_data) != generation
+            {
+                // Keep the known best move if the new result has none
+                let mv = if mv == Move::NULL { old.mv } else { mv };
+                pack(mv, score, eval, depth, bound, generation)
+            } else if mv != Move::NULL {
+                // A shallow result keeps the deeper one, only its best move is newer
+                pack(
+                    mv,
+                    old.score,
+                    old.eval,
+                    old.depth,
+                    old.bound,
+                    data_generation(old_data),
+                )
+            } else {
+                return;
+            }
+        } else {
+            pack(mv, score, eval, depth, bound, generation)
+        };
+
+        replace.key.store(hash ^ data, Ordering::Relaxed);
+        replace.data.store(data, Ordering::Relaxed);
+    }
+
+    /// Returns the permille of sampled entries written during the current search
+    pub fn hashfull(&self) -> usize {
+        let generation = self.generation.load(Ordering::Relaxed);
+        let sample = self.buckets.len().min(1000);
+        let used = self.buckets[..sample]
+            .iter()
+            .flat_map(|bucket| &bucket.slots)
+            .filter(|slot| {
+                let data = slot.data.load(Ordering::Relaxed);
+                data != 0 && data_generation(data) == generation
+            })
+            .count();
+        used * 1000 / (sample * BUCKET_SIZE)
+    }
+
+    fn bucket(&self, hash: u64) -> &Bucket {
+        // Maps the hash onto the table length without a division
+        let idx = ((hash as u128 * self.buckets.len() as u128) >> 64) as usize;
+        &self.buckets[idx]
+    }
+}
+
+/// Packs an entry into a word: move (16 bits), score (16), eval (16), depth (8), bound (2) and
+/// generation (6). A bound is always set, so a used entry is never zero.
+fn pack(mv: Move, score: i32, eval: i32, depth: usize, bound: Bound, generation: u8) -> u64 {
+    mv.get_raw() as u64
+        | (score as i16 as u16 as u64) << 16
+        | (eval as i16 as u16 as u64) << 32
+        | (depth.min(u8::MAX as usize) as u64) << 48
+        | (bound as u64) << 56
+        | (generation as u64) << 58
+}
+
+fn unpack(data: u64) -> TtEntry {
+    TtEntry {
+        mv: Move::from_raw(data as u16),
+        score: (data >> 16) as u16 as i16 as i32,
+        eval: (data >> 32) as u16 as i16 as i32,
+        depth: data_depth(data),
+        bound: match (data >> 56) & 0b11 {
+            1 => Bound::Upper,
+            2 => Bound::Lower,
+            3 => Bound::Exact,
+            _ => Bound::None,
+        },
+    }
+}
+
+fn data_depth(data: u64) -> usize {
+    ((data >> 48) & 0xff) as usize
+}
+
+fn data_generation(data: u64) -> u8 {
+    (data >> 58) as u8 & GENERATION_MASK
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cmove::MoveFlags;
+
+    use super::*;
+
+    #[test]
+    fn store_and_probe_entries() {
+        let tt = TranspositionTable::new(1);
+        assert!(size_of::<Bucket>() == 64);
+        assert!(tt.size_mb() == 1);
+
+        let mv = Move::new(12, 28, MoveFlags::DOUBLE_PAWN_PUSH);
+        tt.store(0xDEAD_BEEF, mv, -30_950, 17, 9, Bound::Lower);
+        let entry = tt.probe(0xDEAD_BEEF).unwrap();
+        assert!(entry.mv == mv);
+        assert!(entry.score == -30_950);
+        assert!(entry.eval == 17);
+        assert!(entry.depth == 9);
+        assert!(entry.bound == Bound::Lower);
+        assert!(tt.probe(0xDEAD_BEEE).is_none());
+
+        // A result without a move keeps the stored move
+        tt.store(0xDEAD_BEEF, Move::NULL, 5, 17, 10, Bound::Upper);
+        let entry = tt.probe(0xDEAD_BEEF).unwrap();
+        assert!(entry.mv == mv);
+        assert!(entry.bound == Bound::Upper);
+
+        tt.clear();
+        assert!(tt.probe(0xDEAD_BEEF).is_none());
+    }
+
+    #[test]
+    fn replace_shallow_and_old_entries() {
+        let tt = TranspositionTable::new(1);
+        // With a single bucket every hash shares the same slots
+        let tt = TranspositionTable {
+            buckets: (0..1).map(|_| Bucket::default()).collect(),
+            ..tt
+        };
+
+        for (hash, depth) in [(1, 10), (2, 3), (3, 12), (4, 8)] {
+            tt.store(hash, Move::NULL, 0, 0, depth, Bound::Exact);
+        }
+        tt.store(5, Move::NULL, 0, 0, 1, Bound::Exact);
+        assert!(tt.probe(2).is_none());
+        assert!(tt.probe(5).is_some());
+
+        // Entries of previous searches are replaced before deeper ones of the current search
+        tt.new_search();
+        tt.store(6, Move::NULL, 0, 0, 2, Bound::Exact);
+        tt.store(7, Move::NULL, 0, 0, 2, Bound::Exact);
+        assert!(tt.probe(5).is_none());
+        assert!(tt.probe(6).is_some());
+        assert!(tt.probe(7).is_some());
+        assert!(tt.probe(3).is_some());
+    }
+
+    #[test]
+    fn keep_deeper_entries_of_the_same_position() {
+        let tt = TranspositionTable::new(1);
+        let (deep_move, shallow_move) = (
+            Move::new(12, 28, MoveFlags::DOUBLE_PAWN_PUSH),
+            Move::new(12, 20, MoveFlags::QUIET),
+        );
+        tt.store(42, deep_move, 50, 10, 12, Bound::Exact);
+
+        // Quiescence and shallow null window results do not evict the deep entry
+        tt.store(42, Move::NULL, -200, 10, 0, Bound::Upper);
+        tt.store(42, shallow_move, 300, 10, 5, Bound::Lower);
+        let entry = tt.probe(42).unwrap();
+        assert!(entry.score == 50 && entry.depth == 12 && entry.bound == Bound::Exact);
+        assert!(entry.mv == shallow_move);
+
+        // Results almost as deep, exact results and entries of older searches are replaced
+        tt.store(42, Move::NULL, 70, 10, 9, Bound::Lower);
+        assert!(tt.probe(42).unwrap().depth == 9);
+        tt.store(42, Move::NULL, 40, 10, 1, Bound::Exact);
+        assert!(tt.probe(42).unwrap().depth == 1);
+        tt.store(42, deep_move, 50, 10, 12, Bound::Exact);
+        tt.new_search();
+        tt.store(42, Move::NULL, -200, 10, 0, Bound::Upper);
+        let entry = tt.probe(42).unwrap();
+        assert!(entry.depth == 0 && entry.mv == deep_move);
+    }
+
+    #[test]
+    fn report_hashfull_of_current_search() {
+        let tt = TranspositionTable::new(1);
+        assert!(tt.hashfull() == 0);
+        for hash in 0..200_000u64 {
+            tt.store(
+                hash.wrapping_mul(0x9E37_79B9_7F4A_7C15),
+                Move::NULL,
+                0,
+                0,
+                1,
+                Bound::Exact,
+            );
+        }
+        assert!(tt.hashfull() > 900);
+
+        tt.new_search();
+        assert!(tt.hashfull() == 0);
+    }
+}
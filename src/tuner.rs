@@ -0,0 +1,263 @@
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader},
+    path::Path,
+    thread,
+};
+
+use crate::{
+    evaluation::{evaluate_with_params, params::EvalParams},
+    fen::parse_fen,
+    position::Position,
+    util::Color,
+};
+
+/// A position labelled with the result of the game it was taken from
+pub struct TuningEntry {
+    pub position: Position,
+    /// Game result from white's point of view: 1 for a win, 0.5 for a draw and 0 for a loss
+    pub result: f64,
+}
+
+/// Parses a line consisting of a FEN, whose move counters may be missing as in EPD, and a game
+/// result such as `[1-0]`, `"1/2-1/2";` or `0.0`
+pub fn parse_entry(line: &str) -> Result<TuningEntry, &'static str> {
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+    if tokens.len() < 4 {
+        return Err("Missing FEN fields");
+    }
+
+    let mut fen = tokens[..4].join(" ");
+    let counters: Vec<&str> = tokens[4..]
+        .iter()
+        .take(2)
+        .take_while(|token| token.parse::<usize>().is_ok())
+        .copied()
+        .collect();
+    match counters.len() {
+        2 => fen = format!("{} {}", fen, counters.join(" ")),
+        1 => fen = format!("{} {} 1", fen, counters[0]),
+        _ => fen.push_str(" 0 1"),
+    }
+    parse_fen(&fen).map_err(|_| "Invalid FEN")?;
+
+    let result = tokens[4 + counters.len()..]
+        .iter()
+        .find_map(|token| parse_result(token))
+        .ok_or("Missing game result")?;
+
+    Ok(TuningEntry {
+        position: Position::from(fen.as_str()),
+        result,
+    })
+}
+
+fn parse_result(token: &str) -> Option<f64> {
+    let token = token.trim_matches(|ch: char| matches!(ch, '[' | ']' | '"' | ';' | ','));
+    match token {
+        "1-0" | "1.0" => Some(1.0),
+        "0-1" | "0.0" => Some(0.0),
+        "1/2-1/2" | "0.5" => Some(0.5),
+        _ => None,
+    }
+}
+
+/// Loads labelled positions from a file with one entry per line, skipping invalid lines.
+/// Returns the entries and the number of skipped lines.
+pub fn load_entries<P: AsRef<Path>>(path: P) -> io::Result<(Vec<TuningEntry>, usize)> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut entries = Vec::new();
+    let mut skipped = 0;
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match parse_entry(&line) {
+            Ok(entry) => entries.push(entry),
+            Err(_) => skipped += 1,
+        }
+    }
+    Ok((entries, skipped))
+}
+
+/// Maps a centipawn score to an expected game result between 0 and 1
+pub fn sigmoid(score: f64, k: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-k * score / 400.0))
+}
+
+/// Texel tuner minimizing the mean squared error between the game results and the results
+/// predicted by the evaluation
+pub struct Tuner {
+    entries: Vec<TuningEntry>,
+    threads: usize,
+}
+
+impl Tuner {
+    pub fn new(entries: Vec<TuningEntry>, threads: usize) -> Self {
+        Tuner {
+            entries,
+            threads: threads.max(1),
+        }
+    }
+
+    /// Computes the mean squared error of the predicted results over all entries
+    pub fn error(&self, params: &EvalParams, k: f64) -> f64 {
+        if self.entries.is_empty() {
+            return 0.0;
+        }
+
+        let chunk_size = self.entries.len().div_ceil(self.threads);
+        let total: f64 = thread::scope(|scope| {
+            let handles: Vec<_> = self
+                .entries
+                .chunks(chunk_size)
+                .map(|chunk| {
+                    scope.spawn(move || {
+                        chunk
+                            .iter()
+                            .map(|entry| {
+                                let score = evaluate_with_params(&entry.position, params);
+                                let score = match entry.position.side_to_move() {
+                                    Color::White => score,
+                                    Color::Black => -score,
+                                };
+                                (entry.result - sigmoid(score as f64, k)).powi(2)
+                            })
+                            .sum::<f64>()
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().expect("tuner thread panicked"))
+                .sum()
+        });
+        total / self.entries.len() as f64
+    }
+
+    /// Finds the scaling constant of the sigmoid which minimizes the error of the given
+    /// parameters, narrowing down the search range ten times per round
+    pub fn fit_k(&self, params: &EvalParams) -> f64 {
+        let (mut low, mut high) = (0.0, 3.0);
+        let mut best = (low + high) / 2.0;
+        for _ in 0..4 {
+            let step = (high - low) / 10.0;
+            let mut best_error = f64::MAX;
+            for idx in 0..=10 {
+                let k = low + step * idx as f64;
+                let error = self.error(params, k);
+                if error < best_error {
+                    best_error = error;
+                    best = k;
+                }
+            }
+            low = (best - step).max(0.0);
+            high = best + step;
+        }
+        best
+    }
+
+    /// Runs local search: every weight is moved by `step` in both directions and the change is
+    /// kept whenever it lowers the error. Passes are repeated until no weight changes or
+    /// `max_passes` is reached. `on_pass` is called after each pass with its number, the current
+    /// parameters and their error.
+    pub fn tune(
+        &self,
+        params: &EvalParams,
+        k: f64,
+        step: i32,
+        max_passes: usize,
+        mut on_pass: impl FnMut(usize, &EvalParams, f64),
+    ) -> EvalParams {
+        let mut values = params.to_vector();
+        let mut best_error = self.error(params, k);
+
+        for pass in 1..=max_passes {
+            let mut improved = false;
+            for idx in 0..values.len() {
+                for delta in [step, -step] {
+                    values[idx] += delta;
+                    let error = self.error(&EvalParams::from_vector(&values), k);
+                    if error < best_error {
+                        best_error = error;
+                        improved = true;
+                        break;
+                    }
+                    values[idx] -= delta;
+                }
+            }
+
+            on_pass(pass, &EvalParams::from_vector(&values), best_error);
+            if !improved {
+                break;
+            }
+        }
+        EvalParams::from_vector(&values)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        attacks::init_tables,
+        evaluation::{params::DEFAULT_PARAMS, S},
+    };
+
+    use super::*;
+
+    #[test]
+    fn parse_labelled_positions() {
+        init_tables();
+        let entry = parse_entry("4k3/8/8/8/8/8/8/R3K3 w - - 0 1 [1-0]").unwrap();
+        assert!(entry.result == 1.0);
+        assert!(entry.position.side_to_move() == Color::White);
+
+        // EPD without move counters
+        let entry = parse_entry("4k3/8/8/8/8/8/8/R3K3 b - - c9 \"1/2-1/2\";").unwrap();
+        assert!(entry.result == 0.5);
+        let entry = parse_entry("4k3/8/8/8/8/8/8/R3K3 b - - 12 40; 0.0").unwrap();
+        assert!(entry.result == 0.0);
+        assert!(entry.position.halfmove_clock() == 12);
+
+        assert!(parse_entry("4k3/8/8/8/8/8/8/R3K3 w - - 0 1").is_err());
+        assert!(parse_entry("4k3/8/8/8/8/8/8/R3K3 x - - 0 1 [1-0]").is_err());
+        assert!(parse_entry("1-0").is_err());
+        assert!(parse_entry("8/8/8/8/8/8/8/8 w - - 0 1 [1-0]").is_err());
+        assert!(parse_entry("4k3/8/8/8/8/8/8/8/4K3 w - - 0 1 [1-0]").is_err());
+        assert!(parse_entry("4k3/8/8/8/8/8/8/4K3R w - - [1-0]").is_err());
+    }
+
+    #[test]
+    fn fit_the_evaluation_to_results() {
+        init_tables();
+        assert!(sigmoid(0.0, 1.0) == 0.5);
+        assert!(sigmoid(400.0, 1.0) > 0.9);
+
+        let entries = [
+            "4k3/8/8/8/8/8/8/R3K3 w - - 0 1 [1-0]",
+            "4k3/8/8/8/8/8/8/R3K3 b - - 0 1 [1-0]",
+            "r3k3/8/8/8/8/8/8/4K3 w - - 0 1 [0-1]",
+            "4k3/8/8/8/8/8/8/4K3 w - - 0 1 [1/2-1/2]",
+        ]
+        .iter()
+        .map(|line| parse_entry(line).unwrap())
+        .collect();
+        let tuner = Tuner::new(entries, 2);
+
+        // Won rook endings favor a steep curve
+        let k = tuner.fit_k(&DEFAULT_PARAMS);
+        assert!(k > 1.0);
+        assert!(tuner.error(&DEFAULT_PARAMS, k) < tuner.error(&DEFAULT_PARAMS, 0.5));
+
+        // Tuning never increases the error
+        let mut params = DEFAULT_PARAMS.clone();
+        params.material[3] = S(100, 100);
+        let start_error = tuner.error(&params, k);
+        let mut passes = 0;
+        let tuned = tuner.tune(&params, k, 50, 1, |_, _, _| passes += 1);
+        assert!(passes == 1);
+        assert!(tuner.error(&tuned, k) < start_error);
+        assert!(tuned.material[3].1 > 100);
+    }
+}
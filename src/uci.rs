@@ -0,0 +1,477 @@
+use std::{
+    io::{self, BufRead, Write},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::Instant,
+};
+
+use crate::{
+    book::Book,
+    cmove::Move,
+    evaluation::{params::DEFAULT_PARAMS, trace::EvalTrace},
+    history::History,
+    limits::SearchLimits,
+    nnue::Network,
+    position::Position,
+    search::{Search, SearchFeatures},
+    transposition_table::TranspositionTable,
+};
+
+pub const START_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+/// Engine settings changeable with `setoption`
+#[derive(Debug, Clone)]
+pub struct EngineOptions {
+    pub hash_mb: usize,
+    pub own_book: bool,
+    pub book_file: String,
+    pub use_nnue: bool,
+    pub eval_file: String,
+    pub multi_pv: usize,
+    pub features: SearchFeatures,
+}
+
+impl Default for EngineOptions {
+    fn default() -> Self {
+        EngineOptions {
+            hash_mb: 16,
+            own_book: false,
+            book_file: String::from("book.bin"),
+            use_nnue: false,
+            eval_file: String::from("larry.nnue"),
+            multi_pv: 1,
+            features: SearchFeatures::default(),
+        }
+    }
+}
+
+/// State of the UCI front-end between commands
+pub struct Uci {
+    position: Position,
+    options: EngineOptions,
+    stop: Arc<AtomicBool>,
+    tt: Arc<TranspositionTable>,
+    /// Move ordering statistics passed from one search to the next, `None` after a new game
+    history: Arc<Mutex<Option<Box<History>>>>,
+    /// Network loaded from the eval file while `UseNNUE` is enabled
+    network: Option<Arc<Network>>,
+    search_thread: Option<JoinHandle<()>>,
+}
+
+impl Default for Uci {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Uci {
+    pub fn new() -> Self {
+        let options = EngineOptions::default();
+        Uci {
+            position: Position::from(START_FEN),
+            tt: Arc::new(TranspositionTable::new(options.hash_mb)),
+            options,
+            stop: Arc::new(AtomicBool::new(false)),
+            history: Arc::new(Mutex::new(None)),
+            network: None,
+            search_thread: None,
+        }
+    }
+
+    /// Reads commands from stdin until `quit` is received or the input ends
+    pub fn run(&mut self) {
+        let stdin = io::stdin();
+        for line in stdin.lock().lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            if !self.handle_command(&line) {
+                break;
+            }
+        }
+        self.stop_search();
+    }
+
+    /// Handles a single command, returns false if the engine should quit
+    pub fn handle_command(&mut self, line: &str) -> bool {
+        let line = line.trim();
+        let (command, args) = line.split_once(' ').unwrap_or((line, ""));
+
+        match command {
+            "uci" => {
+                send("id name larry");
+                send("id author timojokinen");
+                send(&format!(
+                    "option name Hash type spin default {} min 1 max 65536",
+                    EngineOptions::default().hash_mb
+                ));
+                send("option name OwnBook type check default false");
+                send(&format!(
+                    "option name BookFile type string default {}",
+                    EngineOptions::default().book_file
+                ));
+                send("option name UseNNUE type check default false");
+                send(&format!(
+                    "option name EvalFile type string default {}",
+                    EngineOptions::default().eval_file
+                ));
+                send("option name MultiPV type spin default 1 min 1 max 256");
+                for name in FEATURE_OPTIONS {
+                    send(&format!("option name {} type check default true", name));
+                }
+                send("uciok");
+            }
+            "isready" => send("readyok"),
+            "ucinewgame" => {
+                self.stop_search();
+                self.position = Position::from(START_FEN);
+                self.tt.clear();
+                *self.history.lock().unwrap() = None;
+            }
+            "position" => {
+                self.stop_search();
+                match parse_position(args) {
+                    Some(position) => self.position = position,
+                    None => send(&format!("info string invalid position: {}", args)),
+                }
+            }
+            "go" => self.go(args),
+            "eval" => {
+                for line in EvalTrace::new(&self.position, &DEFAULT_PARAMS)
+                    .to_string()
+                    .lines()
+                {
+                    send(line);
+                }
+                if let Some(network) = &self.network {
+                    let mut position = self.position.clone();
+                    position.set_network(Some(Arc::clone(network)));
+                    let score = position
+                        .nnue()
+                        .map_or(0, |nnue| nnue.evaluate(position.side_to_move()));
+                    send(&format!("NNUE evaluation: {} cp (side to move)", score));
+                }
+            }
+            "stop" => self.stop_search(),
+            "setoption" => self.set_option(args),
+            "quit" => return false,
+            "" => (),
+            _ => send(&format!("info string unknown command: {}", command)),
+        }
+        true
+    }
+
+    fn set_option(&mut self, args: &str) {
+        let Some(args) = args.trim().strip_prefix("name ") else {
+            return;
+        };
+        let (name, value) = match args.split_once(" value ") {
+            Some((name, value)) => (name.trim(), value.trim()),
+            None => (args.trim(), ""),
+        };
+
+        match name.to_lowercase().as_str() {
+            "hash" => match value.parse::<usize>() {
+                Ok(mb) if mb > 0 => {
+                    self.stop_search();
+                    self.options.hash_mb = mb;
+                    match Arc::get_mut(&mut self.tt) {
+                        Some(tt) => tt.resize(mb),
+                        None => self.tt = Arc::new(TranspositionTable::new(mb)),
+                    }
+                }
+                _ => send(&format!("info string invalid Hash value: {}", value)),
+            },
+            "multipv" => match value.parse::<usize>() {
+                Ok(multi_pv) if (1..=256).contains(&multi_pv) => self.options.multi_pv = multi_pv,
+                _ => send(&format!("info string invalid MultiPV value: {}", value)),
+            },
+            "ownbook" => self.options.own_book = value == "true",
+            "bookfile" => self.options.book_file = value.to_string(),
+            "usennue" => {
+                self.options.use_nnue = value == "true";
+                self.load_network();
+            }
+            "evalfile" => {
+                self.options.eval_file = value.to_string();
+                self.load_network();
+            }
+            option => match feature_option(&mut self.options.features, option) {
+                Some(enabled) => *enabled = value == "true",
+                None => send(&format!("info string unknown option: {}", name)),
+            },
+        }
+    }
+
+    /// Loads the network from the eval file if enabled. The classical evaluation is used when
+    /// the network is disabled or cannot be loaded.
+    fn load_network(&mut self) {
+        self.network = None;
+        if !self.options.use_nnue {
+            return;
+        }
+        match Network::open(&self.options.eval_file) {
+            Ok(network) => {
+                self.network = Some(Arc::new(network));
+                send(&format!(
+                    "info string loaded network {}",
+                    self.options.eval_file
+                ));
+            }
+            Err(err) => send(&format!(
+                "info string cannot load network {}, using classical evaluation: {}",
+                self.options.eval_file, err
+            )),
+        }
+    }
+
+    fn go(&mut self, args: &str) {
+        self.stop_search();
+
+        if let Some(depth) = args.trim().strip_prefix("perft") {
+            let depth = depth.trim().parse::<usize>().unwrap_or(1);
+            self.perft(depth);
+            return;
+        }
+
+        let limits = SearchLimits::parse(args);
+        let mut position = self.position.clone();
+        position.set_network(self.network.clone());
+        let options = self.options.clone();
+        let stop = Arc::clone(&self.stop);
+        stop.store(false, Ordering::SeqCst);
+        let tt = Arc::clone(&self.tt);
+        tt.new_search();
+        let history = Arc::clone(&self.history);
+
+        self.search_thread = Some(thread::spawn(move || {
+            let line = think(&position, &limits, &options, &stop, &tt, &history);
+            send(&format_best_move(&line));
+        }));
+    }
+
+    /// Prints the perft count of every root move and the total
+    fn perft(&mut self, depth: usize) {
+        let start = Instant::now();
+        let counts = self.position.divide(depth);
+        for (mv, nodes) in &counts {
+            send(&format!("{}: {}", mv, nodes));
+        }
+        let nodes: u64 = counts.iter().map(|(_, nodes)| nodes).sum();
+        send("");
+        send(&format!("Nodes searched: {}", nodes));
+        send(&format!("Time: {} ms", start.elapsed().as_millis()));
+    }
+
+    /// Signals a running search to stop and waits for it to report its best move
+    fn stop_search(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(handle) = self.search_thread.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Chooses the move to play within the given limits, followed by the expected reply if known
+fn think(
+    position: &Position,
+    limits: &SearchLimits,
+    options: &EngineOptions,
+    stop: &AtomicBool,
+    tt: &TranspositionTable,
+    history: &Mutex<Option<Box<History>>>,
+) -> Vec<Move> {
+    if options.own_book {
+        if let Some(mv) = book_move(position, &options.book_file) {
+            return vec![mv];
+        }
+    }
+
+    let mut history = history.lock().unwrap();
+    let mut search = Search::new(position.clone(), limits.clone(), stop, tt);
+    search.set_features(options.features);
+    search.set_multi_pv(options.multi_pv);
+    if let Some(history) = history.take() {
+        search.set_history(history);
+    }
+    let result = search.run();
+    *history = Some(search.into_history());
+    if result.pv.is_empty() {
+        vec![result.best_move]
+    } else {
+        result.pv
+    }
+}
+
+/// UCI options switching the selective search techniques, for SPRT tests of each one
+const FEATURE_OPTIONS: [&str; 6] = [
+    "NullMovePruning",
+    "LateMoveReductions",
+    "ReverseFutilityPruning",
+    "FutilityPruning",
+    "LateMovePruning",
+    "CheckExtensions",
+];
+
+/// Returns the switch of the search feature with the given lowercase option name
+fn feature_option<'a>(features: &'a mut SearchFeatures, name: &str) -> Option<&'a mut bool> {
+    match name {
+        "nullmovepruning" => Some(&mut features.null_move_pruning),
+        "latemovereductions" => Some(&mut features.late_move_reductions),
+        "reversefutilitypruning" => Some(&mut features.reverse_futility_pruning),
+        "futilitypruning" => Some(&mut features.futility_pruning),
+        "latemovepruning" => Some(&mut features.late_move_pruning),
+        "checkextensions" => Some(&mut features.check_extensions),
+        _ => None,
+    }
+}
+
+/// Picks a weighted random move from the opening book
+fn book_move(position: &Position, book_file: &str) -> Option<Move> {
+    let mut book = Book::open(book_file)
+        .map_err(|err| {
+            send(&format!(
+                "info string cannot open book {}: {}",
+                book_file, err
+            ))
+        })
+        .ok()?;
+    book.weighted_move(position, &mut rand::thread_rng())
+        .ok()
+        .flatten()
+}
+
+/// Parses the arguments of the `position` command: `startpos|fen <fen> [moves <move>...]`
+pub fn parse_position(args: &str) -> Option<Position> {
+    let args = args.trim();
+    let (setup, moves) = match args.split_once("moves") {
+        Some((setup, moves)) => (setup.trim(), moves),
+        None => (args, ""),
+    };
+
+    let mut position = if setup == "startpos" {
+        Position::from(START_FEN)
+    } else {
+        let fen = setup.strip_prefix("fen")?.trim();
+        crate::fen::parse_fen(fen).ok()?;
+        let position = Position::from(fen);
+        // The search would capture the king
+        if position.opponent_in_check() {
+            return None;
+        }
+        position
+    };
+
+    for uci_move in moves.split_whitespace() {
+        let mv = position.parse_move(uci_move)?;
+        position.make_move(mv);
+    }
+    Some(position)
+}
+
+/// Formats the `bestmove` command with a ponder move if the line has one. Without a legal move
+/// the null move `0000` is sent.
+fn format_best_move(line: &[Move]) -> String {
+    match line {
+        [] => String::from("bestmove 0000"),
+        [best_move, ..] if *best_move == Move::NULL => String::from("bestmove 0000"),
+        [best_move] => format!("bestmove {}", best_move),
+        [best_move, ponder, ..] => format!("bestmove {} ponder {}", best_move, ponder),
+    }
+}
+
+/// Writes a line to stdout and flushes it immediately, as the GUI is waiting for it
+pub fn send(line: &str) {
+    let mut stdout = io::stdout().lock();
+    let _ = writeln!(stdout, "{}", line);
+    let _ = stdout.flush();
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::attacks::init_tables;
+
+    use super::*;
+
+    #[test]
+    fn parse_position_command_correctly() {
+        init_tables();
+        let position = parse_position("startpos moves e2e4 e7e5 g1f3").unwrap();
+        assert!(
+            position.to_fen() == "rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2"
+        );
+
+        let position = parse_position("fen 4k3/1P6/8/8/8/8/8/4K3 w - - 0 1 moves b7b8n").unwrap();
+        assert!(position.to_fen() == "1N2k3/8/8/8/8/8/8/4K3 b - - 0 1");
+
+        assert!(parse_position("startpos moves e2e5").is_none());
+        assert!(parse_position("fen 4k3/8/8/8/8/8/8/4K3 x - - 0 1").is_none());
+        assert!(parse_position("fen 8/8/8/8/8/8/8/8/4K3 w - - 0 1").is_none());
+        assert!(parse_position("fen 4k3/8/8/8/8/8/8/8 w - - 0 1").is_none());
+        assert!(parse_position("fen").is_none());
+        assert!(parse_position("fen 4k3/4R3/8/8/8/8/8/4K3 w - - 0 1").is_none());
+        assert!(parse_position("fen 4k2P/8/8/8/8/8/8/4K3 b - - 0 1").is_none());
+        let position = parse_position("fen 4k3/8/8/8/8/8/8/4K3 w - - moves e1e2").unwrap();
+        assert!(position.to_fen() == "4k3/8/8/8/8/8/4K3/8 b - - 1 1");
+    }
+
+    #[test]
+    fn keep_the_previous_position_when_the_new_one_is_invalid() {
+        init_tables();
+        let mut uci = Uci::new();
+        uci.handle_command("position fen 4k3/4R3/8/8/8/8/8/4K3 w - - 0 1");
+        // A following `go` searches the previous position instead of capturing the king
+        assert!(uci.position.to_fen() == START_FEN);
+    }
+
+    #[test]
+    fn handle_options_correctly() {
+        init_tables();
+        let mut uci = Uci::new();
+        uci.handle_command("setoption name Hash value 128");
+        uci.handle_command("setoption name OwnBook value true");
+        uci.handle_command("setoption name BookFile value /tmp/my book.bin");
+
+        assert!(uci.options.hash_mb == 128);
+        assert!(uci.tt.size_mb() == 128);
+        assert!(uci.options.own_book);
+        assert!(uci.options.book_file == "/tmp/my book.bin");
+
+        uci.handle_command("setoption name MultiPV value 3");
+        assert!(uci.options.multi_pv == 3);
+        uci.handle_command("setoption name MultiPV value 0");
+        assert!(uci.options.multi_pv == 3);
+
+        uci.handle_command("setoption name NullMovePruning value false");
+        uci.handle_command("setoption name CheckExtensions value false");
+        assert!(!uci.options.features.null_move_pruning);
+        assert!(!uci.options.features.check_extensions);
+        assert!(uci.options.features.late_move_reductions);
+        for name in FEATURE_OPTIONS {
+            assert!(feature_option(&mut uci.options.features, &name.to_lowercase()).is_some());
+        }
+        assert!(!uci.handle_command("quit"));
+    }
+
+    #[test]
+    fn load_network_when_enabled() {
+        init_tables();
+        let path = std::env::temp_dir().join("larry_uci_test.nnue");
+        Network::zeroed()
+            .write(std::fs::File::create(&path).unwrap())
+            .unwrap();
+
+        let mut uci = Uci::new();
+        uci.handle_command(&format!("setoption name EvalFile value {}", path.display()));
+        assert!(uci.network.is_none());
+        uci.handle_command("setoption name UseNNUE value true");
+        assert!(uci.network.is_some());
+
+        uci.handle_command("setoption name EvalFile value /nonexistent.nnue");
+        assert!(uci.network.is_none());
+        std::fs::remove_file(path).unwrap();
+    }
+}
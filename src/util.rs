@@ -2,7 +2,7 @@ use crate::{fen::Squares, piece::Piece};
 
 pub type Bitboard = u64;
 
-#[derive(PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
 #[repr(u8)]
 pub enum Color {
     White = 0,
@@ -103,6 +103,74 @@ pub fn relative_rank(rank: u8, color: Color) -> Bitboard {
     }
 }
 
+/// Returns the rank index (0-7) of a square as seen from the given color
+pub fn relative_rank_of(square: u8, color: Color) -> u8 {
+    match color {
+        Color::White => sq_to_rank(square),
+        Color::Black => 7 - sq_to_rank(square),
+    }
+}
+
+/// Returns the number of king moves between two squares
+pub fn square_distance(a: u8, b: u8) -> u8 {
+    let file_distance = sq_to_file(a).abs_diff(sq_to_file(b));
+    let rank_distance = sq_to_rank(a).abs_diff(sq_to_rank(b));
+    file_distance.max(rank_distance)
+}
+
+/// Shifts all bits one square towards the h-file
+pub fn shift_east(bb: Bitboard) -> Bitboard {
+    (bb << 1) & !FILES[0]
+}
+
+/// Shifts all bits one square towards the a-file
+pub fn shift_west(bb: Bitboard) -> Bitboard {
+    (bb >> 1) & !FILES[7]
+}
+
+/// Shifts all bits one square forward from the point of view of the given color
+pub fn shift_forward(bb: Bitboard, color: Color) -> Bitboard {
+    match color {
+        Color::White => bb << 8,
+        Color::Black => bb >> 8,
+    }
+}
+
+/// Extends every set bit towards the eighth rank
+pub fn north_fill(bb: Bitboard) -> Bitboard {
+    let mut bb = bb;
+    bb |= bb << 8;
+    bb |= bb << 16;
+    bb | bb << 32
+}
+
+/// Extends every set bit towards the first rank
+pub fn south_fill(bb: Bitboard) -> Bitboard {
+    let mut bb = bb;
+    bb |= bb >> 8;
+    bb |= bb >> 16;
+    bb | bb >> 32
+}
+
+/// Extends every set bit over its whole file
+pub fn file_fill(bb: Bitboard) -> Bitboard {
+    north_fill(bb) | south_fill(bb)
+}
+
+/// Returns the squares in front of the set bits from the point of view of the given color
+pub fn front_span(bb: Bitboard, color: Color) -> Bitboard {
+    match color {
+        Color::White => north_fill(bb) << 8,
+        Color::Black => south_fill(bb) >> 8,
+    }
+}
+
+/// Returns all squares attacked by a set of pawns of the given color
+pub fn pawn_attacks_bb(pawns: Bitboard, color: Color) -> Bitboard {
+    let forward = shift_forward(pawns, color);
+    shift_east(forward) | shift_west(forward)
+}
+
 /// Creates a bitboard from a square index
 pub fn bb_from_square(square: u8) -> Bitboard {
     1u64.checked_shl(square.into()).unwrap_or(0)
@@ -234,4 +302,31 @@ mod tests {
         assert!(opp(Color::White) == Color::Black);
         assert!(opp(Color::Black) == Color::White);
     }
+
+    #[test]
+    fn fill_and_shift_bitboards_correctly() {
+        // e4
+        let bb = bb_from_square(28);
+        assert!(north_fill(bb) == FILES[4] & !0xffffff);
+        assert!(south_fill(bb) == FILES[4] & 0xffffffff);
+        assert!(file_fill(bb) == FILES[4]);
+        assert!(front_span(bb, Color::White) == FILES[4] & !0xffffffff);
+        assert!(front_span(bb, Color::Black) == FILES[4] & 0xffffff);
+
+        assert!(shift_east(FILES[7]) == 0);
+        assert!(shift_west(FILES[0]) == 0);
+        assert!(
+            pawn_attacks_bb(bb_from_square(8) | bb_from_square(28), Color::White)
+                == bb_from_square(17) | bb_from_square(35) | bb_from_square(37)
+        );
+        assert!(pawn_attacks_bb(bb_from_square(55), Color::Black) == bb_from_square(46));
+    }
+
+    #[test]
+    fn measure_squares_correctly() {
+        assert!(square_distance(0, 63) == 7);
+        assert!(square_distance(28, 30) == 2);
+        assert!(relative_rank_of(12, Color::White) == 1);
+        assert!(relative_rank_of(12, Color::Black) == 6);
+    }
 }
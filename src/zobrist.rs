@@ -0,0 +1,94 @@
+use crate::{castling_rights::CastlingRights, piece::Piece};
+
+/// Random keys for every hashed component of a position
+pub struct ZobristKeys {
+    pub pieces: [[u64; 64]; 12],
+    pub side: u64,
+    pub castling: [u64; 4],
+    pub en_passant_file: [u64; 8],
+}
+
+/// Zobrist keys, generated at compile time from a fixed seed so hashes are stable between runs
+pub static ZOBRIST: ZobristKeys = generate_keys(0x1A2B_3C4D_5E6F_7081);
+
+/// SplitMix64 step, returns the next state and the generated number
+const fn split_mix(state: u64) -> (u64, u64) {
+    let state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    (state, z ^ (z >> 31))
+}
+
+const fn generate_keys(seed: u64) -> ZobristKeys {
+    let mut state = seed;
+    let mut keys = ZobristKeys {
+        pieces: [[0; 64]; 12],
+        side: 0,
+        castling: [0; 4],
+        en_passant_file: [0; 8],
+    };
+
+    let mut piece = 0;
+    while piece < 12 {
+        let mut square = 0;
+        while square < 64 {
+            let (next, key) = split_mix(state);
+            state = next;
+            keys.pieces[piece][square] = key;
+            square += 1;
+        }
+        piece += 1;
+    }
+
+    let (next, key) = split_mix(state);
+    state = next;
+    keys.side = key;
+
+    let mut idx = 0;
+    while idx < 4 {
+        let (next, key) = split_mix(state);
+        state = next;
+        keys.castling[idx] = key;
+        idx += 1;
+    }
+
+    let mut file = 0;
+    while file < 8 {
+        let (next, key) = split_mix(state);
+        state = next;
+        keys.en_passant_file[file] = key;
+        file += 1;
+    }
+
+    keys
+}
+
+/// Returns the key of a piece standing on a square
+pub fn piece_key(piece: Piece, square: u8) -> u64 {
+    ZOBRIST.pieces[piece.get_index()][square as usize]
+}
+
+/// Returns the combined key of all set castling rights
+pub fn castling_key(castling_rights: CastlingRights) -> u64 {
+    let mut key = 0;
+    for (idx, right) in [
+        CastlingRights::WHITE_KING_SIDE,
+        CastlingRights::WHITE_QUEEN_SIDE,
+        CastlingRights::BLACK_KING_SIDE,
+        CastlingRights::BLACK_QUEEN_SIDE,
+    ]
+    .iter()
+    .enumerate()
+    {
+        if castling_rights.contains(*right) {
+            key ^= ZOBRIST.castling[idx];
+        }
+    }
+    key
+}
+
+/// Returns the key of the file of an en passant square
+pub fn en_passant_key(square: u8) -> u64 {
+    ZOBRIST.en_passant_file[(square & 7) as usize]
+}